@@ -0,0 +1,406 @@
+use std::{
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{Ipv4Addr, TcpListener, TcpStream},
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+use valence::{math::IVec3, prelude::*};
+
+use crate::{
+    bounds::{union, Bounds, Occupancy},
+    json,
+    origin::Origin,
+    section::Section,
+    structure::Structure,
+    vstruc,
+    workspace::{load_structure, plot_area, CurrentPath, Dirty, Plots, Workspace},
+    EditorLayer,
+};
+
+/// How long a connection waits for the editor to answer
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(5);
+/// Largest request body that is accepted
+const MAX_BODY: usize = 64 * 1024 * 1024;
+
+/// An http server on localhost for other tools to read and change workspaces.
+/// Anyone who can connect to it can change every workspace, so it only listens on localhost.
+///
+/// - `GET /workspaces` lists the workspaces
+/// - `GET /workspaces/<name>/structure` gets the structure in the binary format
+/// - `GET /workspaces/<name>/structure.json` gets the structure as json
+/// - `PUT` to either of them loads a structure into the workspace, add `?force` to discard unsaved changes
+/// - `PUT /workspaces/<name>/origin` with `{"x": 0, "y": 0, "z": 0}` moves the origin,
+///   relative to the lowest corner of the structure, it has to stay in the plot of the workspace
+pub struct ApiPlugin {
+    pub port: u16,
+}
+
+impl Plugin for ApiPlugin {
+    fn build(&self, app: &mut App) {
+        let (sender, receiver) = mpsc::channel();
+        let listener = match TcpListener::bind((Ipv4Addr::LOCALHOST, self.port)) {
+            Ok(listener) => listener,
+            Err(e) => {
+                eprintln!("could not start the api on port {}: {}", self.port, e);
+                return;
+            }
+        };
+        thread::spawn(move || serve(listener, sender));
+        app.insert_resource(ApiRequests(Mutex::new(receiver)))
+            .add_systems(Update, handle_api_requests);
+    }
+}
+
+#[derive(Resource)]
+struct ApiRequests(Mutex<Receiver<ApiRequest>>);
+
+struct ApiRequest {
+    method: String,
+    path: String,
+    query: String,
+    body: Vec<u8>,
+    response: Sender<ApiResponse>,
+}
+
+struct ApiResponse {
+    status: u16,
+    content_type: &'static str,
+    body: Vec<u8>,
+}
+
+impl ApiResponse {
+    fn json(value: &impl Serialize) -> Self {
+        Self {
+            status: 200,
+            content_type: "application/json",
+            body: serde_json::to_vec(value).unwrap(),
+        }
+    }
+
+    fn error(status: u16, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            content_type: "text/plain; charset=utf-8",
+            body: message.into().into_bytes(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct WorkspaceInfo<'a> {
+    name: &'a str,
+    owner: &'a str,
+    path: Option<String>,
+    dirty: bool,
+}
+
+#[derive(Deserialize)]
+struct OriginBody {
+    x: i32,
+    y: i32,
+    z: i32,
+}
+
+/// Every connection gets its own thread, so a slow client does not block the others.
+/// The requests are still handled one at a time by the editor.
+fn serve(listener: TcpListener, requests: Sender<ApiRequest>) {
+    for stream in listener.incoming() {
+        let Ok(stream) = stream else {
+            continue;
+        };
+        let requests = requests.clone();
+        thread::spawn(move || {
+            if let Err(e) = handle_connection(stream, &requests) {
+                eprintln!("api connection failed: {}", e);
+            }
+        });
+    }
+}
+
+fn handle_connection(mut stream: TcpStream, requests: &Sender<ApiRequest>) -> io::Result<()> {
+    stream.set_read_timeout(Some(RESPONSE_TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut parts = request_line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        return write_response(&mut stream, ApiResponse::error(400, "invalid request"));
+    };
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+
+    let mut content_length = 0;
+    loop {
+        let mut header = String::new();
+        reader.read_line(&mut header)?;
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse().unwrap_or(0);
+            }
+        }
+    }
+    if content_length > MAX_BODY {
+        return write_response(&mut stream, ApiResponse::error(413, "body is too large"));
+    }
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body)?;
+
+    let (response, receiver) = mpsc::channel();
+    let request = ApiRequest {
+        method: method.to_string(),
+        path: path.to_string(),
+        query: query.to_string(),
+        body,
+        response,
+    };
+    let response = match requests.send(request) {
+        Ok(()) => receiver
+            .recv_timeout(RESPONSE_TIMEOUT)
+            .unwrap_or_else(|_| ApiResponse::error(503, "the editor did not respond")),
+        Err(_) => ApiResponse::error(503, "the editor has stopped"),
+    };
+    write_response(&mut stream, response)
+}
+
+fn write_response(stream: &mut TcpStream, response: ApiResponse) -> io::Result<()> {
+    let reason = match response.status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        409 => "Conflict",
+        413 => "Payload Too Large",
+        500 => "Internal Server Error",
+        _ => "Service Unavailable",
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        response.status,
+        reason,
+        response.content_type,
+        response.body.len()
+    )?;
+    stream.write_all(&response.body)?;
+    stream.flush()
+}
+
+/// Whether the query has the key, like `force` in `?force`, `?force=1` or `?a=b&force`
+fn has_query_key(query: &str, key: &str) -> bool {
+    query
+        .split('&')
+        .any(|pair| percent_decode(pair.split('=').next().unwrap_or_default()) == key)
+}
+
+/// Decodes `%20` and the like, so workspace names can contain any character
+fn percent_decode(string: &str) -> String {
+    let bytes = string.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+fn handle_api_requests(
+    requests: Res<ApiRequests>,
+    mut workspaces: Query<(
+        &Workspace,
+        &CurrentPath,
+        &mut Dirty,
+        &mut Bounds,
+        &mut Section,
+        &mut Occupancy,
+    )>,
+    mut origins: Query<&mut Origin>,
+    mut layer: Query<&mut ChunkLayer, With<EditorLayer>>,
+    plots: Res<Plots>,
+) {
+    let Ok(mut layer) = layer.get_single_mut() else {
+        return;
+    };
+    let requests = requests.0.lock().unwrap();
+    while let Ok(request) = requests.try_recv() {
+        let segments: Vec<String> = request
+            .path
+            .trim_matches('/')
+            .split('/')
+            .map(percent_decode)
+            .collect();
+        let segments: Vec<&str> = segments.iter().map(String::as_str).collect();
+        let response = match (request.method.as_str(), segments.as_slice()) {
+            ("GET", ["workspaces"]) => {
+                let list: Vec<WorkspaceInfo> = workspaces
+                    .iter()
+                    .map(|(workspace, path, dirty, ..)| WorkspaceInfo {
+                        name: &workspace.name,
+                        owner: &workspace.owner,
+                        path: path.0.as_ref().map(|path| path.display().to_string()),
+                        dirty: dirty.0,
+                    })
+                    .collect();
+                ApiResponse::json(&list)
+            }
+            (method, ["workspaces", name, resource]) => {
+                let Some((workspace, _, mut dirty, mut bounds, mut section, mut occupancy)) =
+                    workspaces
+                        .iter_mut()
+                        .find(|(workspace, ..)| workspace.name == *name)
+                else {
+                    let _ = request
+                        .response
+                        .send(ApiResponse::error(404, format!("no workspace '{}'", name)));
+                    continue;
+                };
+                let Ok(mut origin) = origins.get_mut(workspace.origin) else {
+                    let _ = request
+                        .response
+                        .send(ApiResponse::error(404, "the workspace has no origin"));
+                    continue;
+                };
+                match (method, *resource) {
+                    ("GET", "structure" | "structure.json") => {
+                        let structure = Structure::from_section(
+                            &layer,
+                            section.position,
+                            section.size,
+                            origin.position,
+                        );
+                        if *resource == "structure" {
+                            ApiResponse {
+                                status: 200,
+                                content_type: "application/octet-stream",
+                                body: vstruc::encode(&structure),
+                            }
+                        } else {
+                            match json::to_json(&structure) {
+                                Ok(body) => ApiResponse {
+                                    status: 200,
+                                    content_type: "application/json",
+                                    body,
+                                },
+                                Err(e) => ApiResponse::error(500, e),
+                            }
+                        }
+                    }
+                    ("PUT", "structure" | "structure.json") => {
+                        let structure = if *resource == "structure" {
+                            vstruc::decode(&request.body)
+                        } else {
+                            json::from_json(&request.body)
+                        };
+                        match structure {
+                            Err(e) => ApiResponse::error(400, e),
+                            Ok(_) if dirty.0 && !has_query_key(&request.query, "force") => ApiResponse::error(
+                                409,
+                                "the workspace has unsaved changes, add '?force' to discard them",
+                            ),
+                            Ok(structure) => match load_structure(
+                                &mut origin,
+                                &mut bounds,
+                                &mut section,
+                                &mut occupancy,
+                                &mut layer,
+                                &structure,
+                                plots.center(workspace.plot),
+                            ) {
+                                Ok(()) => {
+                                    // the blocks no longer match the file
+                                    dirty.set_if_neq(Dirty(true));
+                                    ApiResponse::json(&"loaded")
+                                }
+                                Err(e) => ApiResponse::error(400, e),
+                            },
+                        }
+                    }
+                    ("PUT", "origin") => {
+                        match serde_json::from_slice::<OriginBody>(&request.body) {
+                            Ok(body) => {
+                                let plot = plot_area(&layer, plots.center(workspace.plot));
+                                match checked_offset(
+                                    section.position,
+                                    IVec3::new(body.x, body.y, body.z),
+                                ) {
+                                    Some(position)
+                                        if union(plot, (position, IVec3::ONE)) == plot =>
+                                    {
+                                        origin.position = position;
+                                        dirty.set_if_neq(Dirty(true));
+                                        ApiResponse::json(&"moved")
+                                    }
+                                    _ => ApiResponse::error(
+                                        400,
+                                        "the origin has to be in the plot of the workspace",
+                                    ),
+                                }
+                            }
+                            Err(e) => ApiResponse::error(400, format!("invalid origin: {}", e)),
+                        }
+                    }
+                    _ => ApiResponse::error(404, "unknown endpoint"),
+                }
+            }
+            _ => ApiResponse::error(404, "unknown endpoint"),
+        };
+        // the connection may have timed out
+        let _ = request.response.send(response);
+    }
+}
+
+/// `None` if a coordinate does not fit in an `i32`
+fn checked_offset(pos: BlockPos, offset: IVec3) -> Option<BlockPos> {
+    Some(BlockPos::new(
+        pos.x.checked_add(offset.x)?,
+        pos.y.checked_add(offset.y)?,
+        pos.z.checked_add(offset.z)?,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn query_keys_are_found_anywhere_in_the_query() {
+        for query in ["force", "force=1", "a=b&force", "a=b&force=true&c"] {
+            assert!(has_query_key(query, "force"), "{}", query);
+        }
+        for query in ["", "forced", "a=force", "a=b"] {
+            assert!(!has_query_key(query, "force"), "{}", query);
+        }
+    }
+
+    #[test]
+    fn offsets_past_the_end_of_i32_are_none() {
+        let pos = BlockPos::new(1, -1, 0);
+        assert_eq!(
+            checked_offset(pos, IVec3::new(1, 2, 3)),
+            Some(BlockPos::new(2, 1, 3))
+        );
+        assert_eq!(checked_offset(pos, IVec3::new(i32::MAX, 0, 0)), None);
+        assert_eq!(checked_offset(pos, IVec3::new(0, i32::MIN, 0)), None);
+    }
+}
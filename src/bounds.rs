@@ -0,0 +1,359 @@
+use std::collections::BTreeMap;
+
+use valence::{math::IVec3, prelude::*};
+
+use crate::section::Section;
+
+/// component for the section that wraps the structure
+#[derive(Component, Default)]
+pub struct Bounds {
+    /// locked bounds never shrink, which allows for air padding around the blocks
+    pub locked: bool,
+}
+
+impl Bounds {
+    /// Updates the section after blocks have changed,
+    /// it is only marked as changed if it was moved or resized
+    pub fn update(&self, section: &mut Mut<Section>, occupancy: &Occupancy) {
+        let current = (section.position, section.size);
+        let area = self.area(current, occupancy);
+        if area != current {
+            (section.position, section.size) = area;
+        }
+    }
+
+    /// Position and size of the section with the blocks, given its current position and size.
+    /// Unlocked bounds fit the blocks, locked bounds grow to contain them,
+    /// and the area is left as is if there are no blocks.
+    pub fn area(&self, current: (BlockPos, IVec3), occupancy: &Occupancy) -> (BlockPos, IVec3) {
+        match occupancy.extent() {
+            Some(extent) if self.locked => union(current, extent),
+            Some(extent) => extent,
+            None => current,
+        }
+    }
+}
+
+/// Number of non-air blocks on every coordinate of each axis.
+/// Keeps the bounds up to date without scanning the layer on every edit.
+#[derive(Component, Default)]
+pub struct Occupancy {
+    axes: [BTreeMap<i32, u32>; 3],
+}
+
+impl Occupancy {
+    /// Counts the blocks in the given area, used after bulk operations like loading
+    pub fn from_layer(layer: &ChunkLayer, position: BlockPos, size: IVec3) -> Self {
+        let mut occupancy = Self::default();
+        for x in 0..size.x {
+            for y in 0..size.y {
+                for z in 0..size.z {
+                    let pos = position + IVec3::new(x, y, z);
+                    let solid = layer
+                        .block(pos)
+                        .map(|block| is_solid(block.state))
+                        .unwrap_or(false);
+                    if solid {
+                        occupancy.add(pos);
+                    }
+                }
+            }
+        }
+        occupancy
+    }
+
+    /// Updates the counts after the block at `pos` changed from `old` to `new`
+    pub fn record(&mut self, pos: BlockPos, old: BlockState, new: BlockState) {
+        match (is_solid(old), is_solid(new)) {
+            (false, true) => self.add(pos),
+            (true, false) => self.remove(pos),
+            _ => {}
+        }
+    }
+
+    /// Position and size of the tightest box containing every block, `None` if there are no blocks
+    pub fn extent(&self) -> Option<(BlockPos, IVec3)> {
+        let mut min = [0; 3];
+        let mut max = [0; 3];
+        for (i, axis) in self.axes.iter().enumerate() {
+            min[i] = *axis.keys().next()?;
+            max[i] = *axis.keys().next_back()?;
+        }
+        Some((
+            BlockPos::new(min[0], min[1], min[2]),
+            IVec3::new(max[0] - min[0], max[1] - min[1], max[2] - min[2]) + 1,
+        ))
+    }
+
+    /// Whether all blocks are within the given area
+    pub fn fits_in(&self, position: BlockPos, size: IVec3) -> bool {
+        match self.extent() {
+            Some((start, extent_size)) => {
+                let start = to_ivec(start);
+                start.cmpge(to_ivec(position)).all()
+                    && (start + extent_size).cmple(to_ivec(position) + size).all()
+            }
+            None => true,
+        }
+    }
+
+    /// Sets a block and keeps the counts in sync, does nothing if the chunk is not loaded
+    pub fn set_block(&mut self, layer: &mut ChunkLayer, pos: BlockPos, state: BlockState) {
+        if let Some(old) = layer.set_block(pos, state) {
+            self.record(pos, old.state, state);
+        }
+    }
+
+    fn add(&mut self, pos: BlockPos) {
+        for (axis, coord) in self.axes.iter_mut().zip([pos.x, pos.y, pos.z]) {
+            *axis.entry(coord).or_insert(0) += 1;
+        }
+    }
+
+    fn remove(&mut self, pos: BlockPos) {
+        for (axis, coord) in self.axes.iter_mut().zip([pos.x, pos.y, pos.z]) {
+            if let Some(count) = axis.get_mut(&coord) {
+                *count -= 1;
+                if *count == 0 {
+                    axis.remove(&coord);
+                }
+            }
+        }
+    }
+}
+
+/// The smallest area containing both areas
+pub fn union(a: (BlockPos, IVec3), b: (BlockPos, IVec3)) -> (BlockPos, IVec3) {
+    let start = to_ivec(a.0).min(to_ivec(b.0));
+    let end = (to_ivec(a.0) + a.1).max(to_ivec(b.0) + b.1);
+    (BlockPos::new(start.x, start.y, start.z), end - start)
+}
+
+pub fn is_solid(state: BlockState) -> bool {
+    state != BlockState::AIR
+}
+
+fn to_ivec(pos: BlockPos) -> IVec3 {
+    IVec3::new(pos.x, pos.y, pos.z)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    const EDITS: usize = 2000;
+    const SEEDS: u64 = 20;
+
+    /// Small deterministic rng, so failures can be reproduced from the seed
+    struct XorShift(u64);
+
+    impl XorShift {
+        fn new(seed: u64) -> Self {
+            // xorshift gets stuck on zero
+            Self(seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1)
+        }
+
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn range(&mut self, min: i32, max: i32) -> i32 {
+            min + (self.next() % (max - min) as u64) as i32
+        }
+
+        fn pos(&mut self, min: i32, max: i32) -> BlockPos {
+            BlockPos::new(
+                self.range(min, max),
+                self.range(min, max),
+                self.range(min, max),
+            )
+        }
+    }
+
+    /// The tightest box containing every block, found by looking at all of them
+    fn tight_box(blocks: &HashSet<BlockPos>) -> Option<(BlockPos, IVec3)> {
+        let mut iter = blocks.iter();
+        let first = iter.next()?;
+        let (mut min, mut max) = (to_ivec(*first), to_ivec(*first));
+        for pos in iter {
+            min = min.min(to_ivec(*pos));
+            max = max.max(to_ivec(*pos));
+        }
+        Some((BlockPos::new(min.x, min.y, min.z), max - min + 1))
+    }
+
+    fn contains(outer: (BlockPos, IVec3), inner: (BlockPos, IVec3)) -> bool {
+        union(outer, inner) == outer
+    }
+
+    /// Places or removes a random block, mostly within a small area so blocks are often replaced
+    fn random_edit(rng: &mut XorShift, blocks: &mut HashSet<BlockPos>, occupancy: &mut Occupancy) {
+        let pos = if rng.next() % 10 == 0 {
+            rng.pos(-40, 40)
+        } else {
+            rng.pos(-4, 4)
+        };
+        let old = if blocks.contains(&pos) {
+            BlockState::STONE
+        } else {
+            BlockState::AIR
+        };
+        let new = match rng.next() % 3 {
+            0 => BlockState::AIR,
+            1 => BlockState::STONE,
+            _ => BlockState::STRUCTURE_VOID,
+        };
+        occupancy.record(pos, old, new);
+        if is_solid(new) {
+            blocks.insert(pos);
+        } else {
+            blocks.remove(&pos);
+        }
+    }
+
+    #[test]
+    fn unlocked_bounds_fit_the_blocks() {
+        for seed in 0..SEEDS {
+            let mut rng = XorShift::new(seed);
+            let mut blocks = HashSet::new();
+            let mut occupancy = Occupancy::default();
+            let bounds = Bounds { locked: false };
+            let mut area = (BlockPos::new(0, 0, 0), IVec3::ONE);
+            for edit in 0..EDITS {
+                random_edit(&mut rng, &mut blocks, &mut occupancy);
+                area = bounds.area(area, &occupancy);
+                if let Some(expected) = tight_box(&blocks) {
+                    assert_eq!(area, expected, "seed {} edit {}", seed, edit);
+                }
+                assert_eq!(occupancy.extent(), tight_box(&blocks));
+            }
+        }
+    }
+
+    #[test]
+    fn locked_bounds_only_grow() {
+        for seed in 0..SEEDS {
+            let mut rng = XorShift::new(seed);
+            let mut blocks = HashSet::new();
+            let mut occupancy = Occupancy::default();
+            let bounds = Bounds { locked: true };
+            let mut area = (BlockPos::new(0, 0, 0), IVec3::ONE);
+            for edit in 0..EDITS {
+                random_edit(&mut rng, &mut blocks, &mut occupancy);
+                let previous = area;
+                area = bounds.area(area, &occupancy);
+                assert!(contains(area, previous), "seed {} edit {}", seed, edit);
+                if let Some(tight) = tight_box(&blocks) {
+                    assert!(contains(area, tight), "seed {} edit {}", seed, edit);
+                    // the bounds only grow as far as needed
+                    assert_eq!(area, union(previous, tight), "seed {} edit {}", seed, edit);
+                } else {
+                    assert_eq!(area, previous);
+                }
+            }
+        }
+    }
+
+    /// Runs `Bounds::update` on a section in a world after every edit,
+    /// which is how the editor uses it
+    #[test]
+    fn update_keeps_the_section_around_the_blocks() {
+        for locked in [false, true] {
+            for seed in 0..SEEDS {
+                let mut rng = XorShift::new(seed);
+                let mut blocks = HashSet::new();
+                let mut occupancy = Occupancy::default();
+                let bounds = Bounds { locked };
+                let mut world = World::new();
+                let entity = world.spawn(Section::default()).id();
+                for edit in 0..EDITS {
+                    random_edit(&mut rng, &mut blocks, &mut occupancy);
+                    world.clear_trackers();
+                    let previous = {
+                        let mut section = world.get_mut::<Section>(entity).unwrap();
+                        let previous = (section.position, section.size);
+                        bounds.update(&mut section, &occupancy);
+                        previous
+                    };
+
+                    let section = world.entity(entity).get_ref::<Section>().unwrap();
+                    let area = (section.position, section.size);
+                    let expected = match tight_box(&blocks) {
+                        Some(tight) if locked => union(previous, tight),
+                        Some(tight) => tight,
+                        None => previous,
+                    };
+                    let context = format!("locked {} seed {} edit {}", locked, seed, edit);
+                    assert_eq!(area, expected, "{}", context);
+                    // the section is only marked as changed if it was moved or resized
+                    assert_eq!(section.is_changed(), area != previous, "{}", context);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn unlocking_fits_the_blocks_again() {
+        let mut rng = XorShift::new(1);
+        let mut blocks = HashSet::new();
+        let mut occupancy = Occupancy::default();
+        let mut area = (BlockPos::new(0, 0, 0), IVec3::ONE);
+        for _ in 0..EDITS {
+            random_edit(&mut rng, &mut blocks, &mut occupancy);
+            area = Bounds { locked: true }.area(area, &occupancy);
+        }
+        let area = Bounds { locked: false }.area(area, &occupancy);
+        assert_eq!(Some(area), tight_box(&blocks));
+    }
+
+    #[test]
+    fn fits_in_matches_the_blocks() {
+        for seed in 0..SEEDS {
+            let mut rng = XorShift::new(seed);
+            let mut blocks = HashSet::new();
+            let mut occupancy = Occupancy::default();
+            for _ in 0..EDITS / 10 {
+                random_edit(&mut rng, &mut blocks, &mut occupancy);
+            }
+            for _ in 0..100 {
+                let position = rng.pos(-8, 8);
+                let size = IVec3::new(rng.range(1, 16), rng.range(1, 16), rng.range(1, 16));
+                let expected = blocks
+                    .iter()
+                    .all(|pos| contains((position, size), (*pos, IVec3::ONE)));
+                assert_eq!(occupancy.fits_in(position, size), expected, "seed {}", seed);
+            }
+        }
+    }
+
+    #[test]
+    fn empty_occupancy_keeps_the_area() {
+        let area = (BlockPos::new(3, -2, 7), IVec3::new(2, 5, 1));
+        let occupancy = Occupancy::default();
+        assert_eq!(Bounds { locked: false }.area(area, &occupancy), area);
+        assert_eq!(Bounds { locked: true }.area(area, &occupancy), area);
+    }
+
+    #[test]
+    fn union_contains_both_areas() {
+        let mut rng = XorShift::new(7);
+        for _ in 0..1000 {
+            let a = (
+                rng.pos(-10, 10),
+                IVec3::new(rng.range(1, 8), rng.range(1, 8), rng.range(1, 8)),
+            );
+            let b = (
+                rng.pos(-10, 10),
+                IVec3::new(rng.range(1, 8), rng.range(1, 8), rng.range(1, 8)),
+            );
+            let both = union(a, b);
+            assert_eq!(both, union(b, a));
+            assert!(contains(both, a) && contains(both, b));
+        }
+    }
+}
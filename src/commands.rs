@@ -4,6 +4,7 @@ use std::{
 };
 
 use valence::{
+    client::Username,
     command::{parsers::GreedyString, AddCommand, CommandScopeRegistry},
     math::IVec3,
     prelude::*,
@@ -14,7 +15,25 @@ use command::handler::CommandResultEvent;
 use command_macros::Command;
 use valence::{command, command_macros};
 
-use crate::{origin::Origin, section::Section, structure::Structure, Bounds};
+use crate::{
+    bounds::{union, Bounds, Occupancy},
+    config::{Config, Role, SectionStyle},
+    diff::{self, ChangeKind, DiffMarker},
+    grid::{self, Grid},
+    origin::Origin,
+    palette, save,
+    section::{self, Section},
+    snapshot, stats,
+    structure::Structure,
+    void::{self, STRUCTURE_VOID},
+    watch::Watch,
+    workspace::{self, load_structure, CurrentPath, Dirty, Editing, Personal, Plots, Workspace},
+    EditorLayer,
+};
+
+/// How many block kinds `/stats` lists
+const STATS_KINDS: usize = 10;
+
 pub struct CommandPlugin;
 
 impl Plugin for CommandPlugin {
@@ -22,7 +41,12 @@ impl Plugin for CommandPlugin {
         app.add_systems(
             Startup,
             |mut command_scopes: ResMut<CommandScopeRegistry>| {
-                command_scopes.link("valence.admin", "valence.command")
+                command_scopes.link("valence.admin", "valence.command");
+                for role in [Role::Viewer, Role::Builder, Role::Admin] {
+                    for command in role.commands() {
+                        command_scopes.link(role.scope(), command);
+                    }
+                }
             },
         )
         .add_command::<OriginCommand>()
@@ -30,6 +54,22 @@ impl Plugin for CommandPlugin {
         .add_command::<LoadCommand>()
         .add_command::<PathCommand>()
         .add_command::<NewCommand>()
+        .add_command::<BoundsCommand>()
+        .add_command::<VoidCommand>()
+        .add_command::<PasteCommand>()
+        .add_command::<OpenCommand>()
+        .add_command::<CloseCommand>()
+        .add_command::<SwitchCommand>()
+        .add_command::<VisitCommand>()
+        .add_command::<StatsCommand>()
+        .add_command::<BomCommand>()
+        .add_command::<GridCommand>()
+        .add_command::<WatchCommand>()
+        .add_command::<DiffCommand>()
+        .add_command::<MergeCommand>()
+        .add_command::<SnapshotCommand>()
+        .add_command::<SnapshotsCommand>()
+        .add_command::<RestoreCommand>()
         .add_systems(
             Update,
             (
@@ -38,43 +78,198 @@ impl Plugin for CommandPlugin {
                 handle_load_command,
                 handle_path_command,
                 handle_new_command,
+                handle_bounds_command,
+                handle_bounds_style_command,
+                handle_void_command,
+                handle_paste_command,
+                handle_open_command,
+                handle_close_command,
+                handle_switch_command,
+                handle_visit_command,
+                handle_stats_command,
+                handle_bom_command,
+                handle_grid_command,
+                handle_watch_command,
+                notify_unsaved_changes,
             ),
         )
-        .insert_resource(CurrentPath(None));
+        .add_systems(
+            Update,
+            (
+                handle_diff_command,
+                handle_merge_command,
+                handle_snapshot_command,
+                handle_snapshots_command,
+                handle_restore_command,
+            ),
+        );
     }
 }
 
 #[derive(Command, Debug, Clone)]
 #[paths("save {path?}", "s {path?}")]
 #[scopes("valence.command.save")]
-struct SaveCommand {
-    path: Option<GreedyString>,
+pub struct SaveCommand {
+    pub path: Option<GreedyString>,
 }
 
 #[derive(Command, Debug, Clone)]
 #[paths("load {path}", "l {path}")]
 #[scopes("valence.command.load")]
-struct LoadCommand {
-    path: GreedyString,
+pub struct LoadCommand {
+    pub path: GreedyString,
 }
 
 #[derive(Command, Debug, Clone)]
 #[paths("path", "p")]
 #[scopes("valence.command.path")]
-struct PathCommand;
+pub struct PathCommand;
 
 #[derive(Command, Debug, Clone)]
-#[paths("new")]
+#[paths("new {confirm?}")]
 #[scopes("valence.command.new")]
-struct NewCommand;
+pub struct NewCommand {
+    pub confirm: Option<String>,
+}
+
+#[derive(Command, Debug, Clone)]
+#[paths("bounds", "b")]
+#[scopes("valence.command.bounds")]
+pub enum BoundsCommand {
+    #[paths("lock")]
+    Lock,
+    #[paths("unlock")]
+    Unlock,
+    #[paths("expand {direction} {amount}")]
+    Expand { direction: String, amount: i32 },
+    #[paths("contract {direction} {amount}")]
+    Contract { direction: String, amount: i32 },
+    #[paths("style wall {block}")]
+    StyleWall { block: String },
+    #[paths("style corner {block}")]
+    StyleCorner { block: String },
+    #[paths("style wall_width {width}")]
+    StyleWallWidth { width: f32 },
+    #[paths("style corner_size {size}")]
+    StyleCornerSize { size: f32 },
+    #[paths("style glow {part} {color}")]
+    StyleGlow { part: String, color: String },
+    #[paths("style reset")]
+    StyleReset,
+}
+
+#[derive(Command, Debug, Clone)]
+#[paths("void", "v")]
+#[scopes("valence.command.void")]
+pub enum VoidCommand {
+    #[paths("fill")]
+    Fill,
+    #[paths("clear")]
+    Clear,
+}
+
+#[derive(Command, Debug, Clone)]
+#[paths("paste {path}")]
+#[scopes("valence.command.paste")]
+pub struct PasteCommand {
+    pub path: GreedyString,
+}
+
+#[derive(Command, Debug, Clone)]
+#[paths("open {path?}")]
+#[scopes("valence.command.open")]
+pub struct OpenCommand {
+    pub path: Option<GreedyString>,
+}
+
+#[derive(Command, Debug, Clone)]
+#[paths("close {force?}")]
+#[scopes("valence.command.close")]
+pub struct CloseCommand {
+    pub force: Option<String>,
+}
+
+#[derive(Command, Debug, Clone)]
+#[paths("switch {name?}")]
+#[scopes("valence.command.switch")]
+pub struct SwitchCommand {
+    pub name: Option<String>,
+}
+
+#[derive(Command, Debug, Clone)]
+#[paths("visit {player}")]
+#[scopes("valence.command.visit")]
+pub struct VisitCommand {
+    pub player: String,
+}
+
+#[derive(Command, Debug, Clone)]
+#[paths("stats")]
+#[scopes("valence.command.stats")]
+pub struct StatsCommand;
+
+#[derive(Command, Debug, Clone)]
+#[paths("bom")]
+#[scopes("valence.command.bom")]
+pub struct BomCommand;
+
+#[derive(Command, Debug, Clone)]
+#[paths("grid")]
+#[scopes("valence.command.grid")]
+pub enum GridCommand {
+    #[paths("on {spacing?}")]
+    On { spacing: Option<i32> },
+    #[paths("off")]
+    Off,
+}
+
+#[derive(Command, Debug, Clone)]
+#[paths("watch")]
+#[scopes("valence.command.watch")]
+pub enum WatchCommand {
+    #[paths("on")]
+    On,
+    #[paths("off")]
+    Off,
+}
+
+#[derive(Command, Debug, Clone)]
+#[paths("diff {path?}")]
+#[scopes("valence.command.diff")]
+pub struct DiffCommand {
+    pub path: Option<GreedyString>,
+}
 
-#[derive(Resource)]
-struct CurrentPath(Option<PathBuf>);
+#[derive(Command, Debug, Clone)]
+#[paths("merge {path}")]
+#[scopes("valence.command.merge")]
+pub struct MergeCommand {
+    pub path: GreedyString,
+}
+
+#[derive(Command, Debug, Clone)]
+#[paths("snapshot {name}")]
+#[scopes("valence.command.snapshot")]
+pub struct SnapshotCommand {
+    pub name: GreedyString,
+}
+
+#[derive(Command, Debug, Clone)]
+#[paths("snapshots")]
+#[scopes("valence.command.snapshots")]
+pub struct SnapshotsCommand;
+
+#[derive(Command, Debug, Clone)]
+#[paths("restore {name}")]
+#[scopes("valence.command.restore")]
+pub struct RestoreCommand {
+    pub name: GreedyString,
+}
 
 #[derive(Command, Debug, Clone)]
 #[paths("origin", "o")]
 #[scopes("valence.command.origin")]
-enum OriginCommand {
+pub enum OriginCommand {
     #[paths("up", "u")]
     Up,
     #[paths("down", "d")]
@@ -97,22 +292,27 @@ enum OriginCommand {
 
 fn handle_origin_command(
     mut events: EventReader<CommandResultEvent<OriginCommand>>,
-    mut origin: Query<&mut Origin>,
-    sender: Query<(&Look, &Position)>,
+    mut workspaces: Query<(&Workspace, &mut Dirty)>,
+    mut origins: Query<&mut Origin>,
+    sender: Query<(&Look, &Position, &Editing)>,
 ) {
-    let mut origin = origin.single_mut();
-
     for event in events.read() {
-        let (look, pos) = match sender.get(event.executor) {
+        let (look, pos, editing) = match sender.get(event.executor) {
             Ok(v) => v,
             Err(_) => continue,
         };
+        let Ok((workspace, mut dirty)) = workspaces.get_mut(editing.0) else {
+            continue;
+        };
+        let Ok(mut origin) = origins.get_mut(workspace.origin) else {
+            continue;
+        };
         origin.position = match event.result {
             OriginCommand::Up => origin.position.offset(0, 1, 0),
             OriginCommand::Down => origin.position.offset(0, -1, 0),
             OriginCommand::North => origin.position.offset(0, 0, -1),
             OriginCommand::South => origin.position.offset(0, 0, 1),
-            OriginCommand::East => origin.position.offset(0, 0, -1),
+            OriginCommand::East => origin.position.offset(1, 0, 0),
             OriginCommand::West => origin.position.offset(-1, 0, 0),
             OriginCommand::Forward => origin.position.get_in_direction(super::look_to_dir(look)),
             OriginCommand::Back => {
@@ -130,50 +330,88 @@ fn handle_origin_command(
                 pos.y.floor() as i32,
                 pos.z.floor() as i32,
             ),
-        }
+        };
+        dirty.set_if_neq(Dirty(true));
     }
 }
 
 fn handle_save_command(
     mut events: EventReader<CommandResultEvent<SaveCommand>>,
-    origin: Query<&Origin>,
-    section: Query<&Section, With<Bounds>>,
-    layer: Query<&ChunkLayer>,
-    mut sender: Query<&mut Client>,
-    mut current_path: ResMut<CurrentPath>,
+    origins: Query<&Origin>,
+    mut workspaces: Query<(
+        &Workspace,
+        &Section,
+        &mut CurrentPath,
+        &mut Dirty,
+        Option<&mut Watch>,
+    )>,
+    layer: Query<&ChunkLayer, With<EditorLayer>>,
+    mut sender: Query<(&mut Client, &Editing)>,
 ) {
-    let origin = origin.single();
-    let section = section.single();
-    let layer = layer.single();
+    let Ok(layer) = layer.get_single() else {
+        return;
+    };
     for event in events.read() {
-        let mut client = match sender.get_mut(event.executor) {
+        let (mut client, editing) = match sender.get_mut(event.executor) {
             Ok(c) => c,
             Err(_) => continue,
         };
-        if let Some(path) = event
-            .result
-            .path
-            .as_ref()
-            .map(|str| string_to_path_buf(&str.0))
-            .or(current_path.0.clone())
-        {
+        let Ok((workspace, section, mut current_path, mut dirty, watch)) =
+            workspaces.get_mut(editing.0)
+        else {
+            continue;
+        };
+        let Ok(origin) = origins.get(workspace.origin) else {
+            continue;
+        };
+        let (path, force) = match &event.result.path {
+            Some(path) => {
+                let (path, force) = strip_flag(&path.0, "force");
+                (Some(string_to_path_buf(path)), force)
+            }
+            None => (None, false),
+        };
+        let retarget = path.is_some() && path != current_path.0;
+        if let Some(path) = path.or(current_path.0.clone()) {
+            if retarget && path.exists() && !force {
+                client_error(
+                    &mut client,
+                    format!(
+                        "'{}' already exists, use '/save {} force' to overwrite it",
+                        path.display(),
+                        path.display()
+                    ),
+                );
+                continue;
+            }
+
             let structure =
                 Structure::from_section(layer, section.position, section.size, origin.position);
 
-            if let Err(e) = fs::create_dir_all(&path.parent().unwrap_or(Path::new("")))
-                .or(fs::write(&path, structure.serialize()))
-            {
+            // the path is only changed if the structure was saved
+            if let Err(e) = save::save_structure(&path, &structure) {
                 client_error(
                     &mut client,
-                    format!("an error occured while trying to save: {}", e),
+                    format!("could not save to '{}', {}", path.display(), e),
                 );
-            } else {
+                continue;
+            }
+            client_info(
+                &mut client,
+                format!("saved structure to '{}'", path.display()),
+            );
+            dirty.set_if_neq(Dirty(false));
+            // the watcher should not reload our own save
+            if let Some(mut watch) = watch {
+                watch.record(&path);
+            }
+
+            if retarget {
                 client_info(
                     &mut client,
-                    format!("saved structure to '{}'", path.display()),
-                )
+                    format!("'{}' is now the current path", path.display()),
+                );
             }
-
             current_path.0 = Some(path);
         } else {
             client_error(
@@ -186,121 +424,1344 @@ fn handle_save_command(
 
 fn handle_load_command(
     mut events: EventReader<CommandResultEvent<LoadCommand>>,
-    mut origin: Query<&mut Origin>,
-    mut section: Query<&mut Section, With<Bounds>>,
-    mut layer: Query<&mut ChunkLayer>,
+    mut origins: Query<&mut Origin>,
+    mut workspaces: Query<(
+        &Workspace,
+        &mut Bounds,
+        &mut Section,
+        &mut Occupancy,
+        &mut CurrentPath,
+        &mut Dirty,
+    )>,
+    mut layer: Query<&mut ChunkLayer, With<EditorLayer>>,
+    mut sender: Query<(&mut Client, &Editing)>,
+    plots: Res<Plots>,
+) {
+    let Ok(mut layer) = layer.get_single_mut() else {
+        return;
+    };
+    for event in events.read() {
+        let (mut client, editing) = match sender.get_mut(event.executor) {
+            Ok(c) => c,
+            Err(_) => continue,
+        };
+        let Ok((workspace, mut bounds, mut section, mut occupancy, mut current_path, mut dirty)) =
+            workspaces.get_mut(editing.0)
+        else {
+            continue;
+        };
+        let Ok(mut origin) = origins.get_mut(workspace.origin) else {
+            continue;
+        };
+        let (path, force) = strip_flag(&event.result.path.0, "force");
+        if dirty.0 && !force {
+            client_error(
+                &mut client,
+                format!(
+                    "there are unsaved changes, use '/load {} force' to discard them",
+                    path
+                ),
+            );
+            continue;
+        }
+        let path = string_to_path_buf(path);
+        let structure = match save::read_structure(&path) {
+            Ok(s) => s,
+            Err(e) => {
+                client_error(&mut client, e);
+                continue;
+            }
+        };
+        if let Err(e) = load_structure(
+            &mut origin,
+            &mut bounds,
+            &mut section,
+            &mut occupancy,
+            &mut layer,
+            &structure,
+            plots.center(workspace.plot),
+        ) {
+            client_error(
+                &mut client,
+                format!("could not load '{}', {}", path.display(), e),
+            );
+            continue;
+        }
+        client_info(
+            &mut client,
+            format!("loaded structure '{}'", path.display()),
+        );
+        current_path.0 = Some(path);
+        dirty.set_if_neq(Dirty(false));
+    }
+}
+
+/// Viewers are not editing a workspace, so they get the path of the plot they are in
+fn handle_path_command(
+    mut events: EventReader<CommandResultEvent<PathCommand>>,
+    mut sender: Query<(&mut Client, &Position, Option<&Editing>)>,
+    workspaces: Query<(&Workspace, &CurrentPath)>,
+    plots: Res<Plots>,
+) {
+    for event in events.read() {
+        let Ok((mut client, position, editing)) = sender.get_mut(event.executor) else {
+            continue;
+        };
+        let current_path = match editing {
+            Some(editing) => workspaces.get(editing.0).ok(),
+            None => {
+                let plot = plots.plot_of(BlockPos::new(
+                    position.0.x.floor() as i32,
+                    position.0.y.floor() as i32,
+                    position.0.z.floor() as i32,
+                ));
+                workspaces
+                    .iter()
+                    .find(|(workspace, _)| workspace.plot == plot)
+            }
+        };
+        let Some((_, current_path)) = current_path else {
+            client_error(
+                &mut client,
+                "you are not in a workspace, use '/visit <player>' to go to one".into(),
+            );
+            continue;
+        };
+        let message = if let Some(path) = &current_path.0 {
+            format!("current path: '{}'", path.display())
+        } else {
+            "no path selected".into()
+        };
+        client_info(&mut client, message);
+    }
+}
+
+fn handle_new_command(
+    mut events: EventReader<CommandResultEvent<NewCommand>>,
+    mut origins: Query<&mut Origin>,
+    mut workspaces: Query<(
+        &Workspace,
+        &mut Bounds,
+        &mut Section,
+        &mut Occupancy,
+        &mut CurrentPath,
+        &mut Dirty,
+    )>,
+    mut layer: Query<&mut ChunkLayer, With<EditorLayer>>,
+    mut sender: Query<(&mut Client, &Editing)>,
+    plots: Res<Plots>,
+) {
+    let Ok(mut layer) = layer.get_single_mut() else {
+        return;
+    };
+    for event in events.read() {
+        let (mut client, editing) = match sender.get_mut(event.executor) {
+            Ok(c) => c,
+            Err(_) => continue,
+        };
+        let Ok((workspace, mut bounds, mut section, mut occupancy, mut current_path, mut dirty)) =
+            workspaces.get_mut(editing.0)
+        else {
+            continue;
+        };
+        let Ok(mut origin) = origins.get_mut(workspace.origin) else {
+            continue;
+        };
+        let confirm = match parse_flag(&event.result.confirm, "confirm") {
+            Ok(confirm) => confirm,
+            Err(e) => {
+                client_error(&mut client, e);
+                continue;
+            }
+        };
+        if dirty.0 && !confirm {
+            client_error(
+                &mut client,
+                "there are unsaved changes, use '/new confirm' to discard them".into(),
+            );
+            continue;
+        }
+        if let Err(e) = load_structure(
+            &mut origin,
+            &mut bounds,
+            &mut section,
+            &mut occupancy,
+            &mut layer,
+            &workspace::empty_structure(),
+            plots.center(workspace.plot),
+        ) {
+            client_error(&mut client, e);
+            continue;
+        }
+        client_info(&mut client, "created new structure".into());
+        current_path.0 = None;
+        dirty.set_if_neq(Dirty(false));
+    }
+}
+
+fn handle_open_command(
+    mut events: EventReader<CommandResultEvent<OpenCommand>>,
+    mut commands: Commands,
+    workspaces: Query<(Entity, &Workspace, &CurrentPath)>,
+    mut layer: Query<(Entity, &mut ChunkLayer), With<EditorLayer>>,
+    mut sender: Query<(&mut Client, &mut Position, &Username, &Role)>,
+    config: Res<Config>,
+    plots: Res<Plots>,
+) {
+    let Ok((layer_entity, mut layer)) = layer.get_single_mut() else {
+        return;
+    };
+    // workspaces spawned this tick are not in the query yet
+    let mut opened: Vec<(String, i32)> = Vec::new();
+    for event in events.read() {
+        let (mut client, mut position, username, role) = match sender.get_mut(event.executor) {
+            Ok(c) => c,
+            Err(_) => continue,
+        };
+        let (name, structure, path) = match &event.result.path {
+            Some(path) => {
+                let path = string_to_path_buf(&path.0);
+                if let Some((entity, workspace, _)) = workspaces
+                    .iter()
+                    .find(|(_, _, current_path)| current_path.0.as_ref() == Some(&path))
+                {
+                    if !workspace.can_edit(&username.0, *role) {
+                        client_error(
+                            &mut client,
+                            format!(
+                                "'{}' is already open by {}",
+                                path.display(),
+                                workspace.owner
+                            ),
+                        );
+                        continue;
+                    }
+                    commands.entity(event.executor).insert(Editing(entity));
+                    position.0 = plots.player_spawn(workspace.plot);
+                    client_info(
+                        &mut client,
+                        format!(
+                            "'{}' is already open as '{}'",
+                            path.display(),
+                            workspace.name
+                        ),
+                    );
+                    continue;
+                }
+                let structure = match save::read_structure(&path) {
+                    Ok(s) => s,
+                    Err(e) => {
+                        client_error(&mut client, e);
+                        continue;
+                    }
+                };
+                let name = path
+                    .file_stem()
+                    .map(|stem| stem.to_string_lossy().into_owned())
+                    .unwrap_or_else(|| "untitled".into());
+                (name, structure, Some(path))
+            }
+            None => ("untitled".into(), workspace::empty_structure(), None),
+        };
+        let names: Vec<&str> = workspaces
+            .iter()
+            .map(|(_, workspace, _)| workspace.name.as_str())
+            .chain(opened.iter().map(|(name, _)| name.as_str()))
+            .collect();
+        let name = workspace::unique_name(name, &names);
+        let used_plots: Vec<i32> = workspaces
+            .iter()
+            .map(|(_, workspace, _)| workspace.plot)
+            .chain(opened.iter().map(|(_, plot)| *plot))
+            .collect();
+        let plot = workspace::free_plot(&used_plots);
+
+        let entity = match workspace::spawn_workspace(
+            &mut commands,
+            layer_entity,
+            &mut layer,
+            name.clone(),
+            username.0.clone(),
+            &plots,
+            plot,
+            &structure,
+            path,
+            config.style(&name),
+        ) {
+            Ok(entity) => entity,
+            Err(e) => {
+                client_error(&mut client, format!("could not open '{}', {}", name, e));
+                continue;
+            }
+        };
+        commands.entity(event.executor).insert(Editing(entity));
+        position.0 = plots.player_spawn(plot);
+        client_info(&mut client, format!("opened '{}'", name));
+        opened.push((name, plot));
+    }
+}
+
+fn handle_close_command(
+    mut events: EventReader<CommandResultEvent<CloseCommand>>,
+    mut commands: Commands,
+    workspaces: Query<(Entity, &Workspace, &Section, &Dirty, Has<Personal>)>,
+    mut layer: Query<&mut ChunkLayer, With<EditorLayer>>,
     mut sender: Query<&mut Client>,
-    mut current_path: ResMut<CurrentPath>,
+    mut editors: Query<(Entity, &Editing, &mut Position, &Username)>,
+    plots: Res<Plots>,
 ) {
-    let mut origin = origin.single_mut();
-    let mut section = section.single_mut();
-    let mut layer = layer.single_mut();
+    let Ok(mut layer) = layer.get_single_mut() else {
+        return;
+    };
     for event in events.read() {
         let mut client = match sender.get_mut(event.executor) {
             Ok(c) => c,
             Err(_) => continue,
         };
-        let path = string_to_path_buf(&event.result.path.0);
-        let data = match fs::read(&path) {
-            Ok(d) => d,
+        let Ok((_, editing, ..)) = editors.get(event.executor) else {
+            continue;
+        };
+        let Ok((entity, workspace, section, dirty, personal)) = workspaces.get(editing.0) else {
+            continue;
+        };
+        let force = match parse_flag(&event.result.force, "force") {
+            Ok(force) => force,
             Err(e) => {
-                client_error(
-                    &mut client,
-                    format!("error while trying to read structure data: {}", e),
-                );
+                client_error(&mut client, e);
+                continue;
+            }
+        };
+        if personal {
+            client_error(
+                &mut client,
+                "can not close your own workspace, use '/new' to clear it".into(),
+            );
+            continue;
+        }
+        if dirty.0 && !force {
+            client_error(
+                &mut client,
+                format!(
+                    "'{}' has unsaved changes, use '/close force' to close it anyway",
+                    workspace.name
+                ),
+            );
+            continue;
+        }
+
+        workspace::clear(&mut layer, section);
+        commands.entity(entity).insert(Despawned);
+        commands.entity(workspace.origin).insert(Despawned);
+
+        // everyone editing the closed workspace goes back to their own
+        for (editor, editing, mut position, username) in &mut editors {
+            if editing.0 != entity {
+                continue;
+            }
+            if let Some((fallback, fallback_workspace, ..)) = workspaces
+                .iter()
+                .find(|(_, other, .., personal)| *personal && other.owner == username.0)
+            {
+                commands.entity(editor).insert(Editing(fallback));
+                position.0 = plots.player_spawn(fallback_workspace.plot);
+            }
+        }
+        client_info(&mut client, format!("closed '{}'", workspace.name));
+    }
+}
+
+fn handle_switch_command(
+    mut events: EventReader<CommandResultEvent<SwitchCommand>>,
+    mut commands: Commands,
+    workspaces: Query<(Entity, &Workspace, &Dirty)>,
+    mut sender: Query<(&mut Client, &mut Position, &Username, &Role)>,
+    plots: Res<Plots>,
+) {
+    for event in events.read() {
+        let (mut client, mut position, username, role) = match sender.get_mut(event.executor) {
+            Ok(c) => c,
+            Err(_) => continue,
+        };
+        let mut own = workspaces
+            .iter()
+            .filter(|(_, workspace, _)| workspace.can_edit(&username.0, *role));
+        let Some(name) = &event.result.name else {
+            let mut names: Vec<_> = own
+                .map(|(_, workspace, dirty)| {
+                    format!("{}{}", workspace.name, if dirty.0 { "*" } else { "" })
+                })
+                .collect();
+            names.sort();
+            client_info(
+                &mut client,
+                format!("open structures: {}", names.join(", ")),
+            );
+            continue;
+        };
+        let Some((entity, workspace, _)) = own.find(|(_, workspace, _)| &workspace.name == name)
+        else {
+            client_error(
+                &mut client,
+                format!("you have no structure named '{}' open", name),
+            );
+            continue;
+        };
+        commands.entity(event.executor).insert(Editing(entity));
+        position.0 = plots.player_spawn(workspace.plot);
+        client_info(&mut client, format!("switched to '{}'", name));
+    }
+}
+
+fn handle_visit_command(
+    mut events: EventReader<CommandResultEvent<VisitCommand>>,
+    workspaces: Query<(&Workspace, Has<Personal>)>,
+    players: Query<(&Username, &Editing)>,
+    mut sender: Query<(&mut Client, &mut Position)>,
+    plots: Res<Plots>,
+) {
+    for event in events.read() {
+        let (mut client, mut position) = match sender.get_mut(event.executor) {
+            Ok(c) => c,
+            Err(_) => continue,
+        };
+        let player = &event.result.player;
+        // the workspace the player is editing, or their own if they are offline
+        let workspace = match players.iter().find(|(username, _)| &username.0 == player) {
+            Some((_, editing)) => workspaces.get(editing.0).ok().map(|(w, _)| w),
+            None => workspaces
+                .iter()
+                .find(|(workspace, personal)| *personal && &workspace.owner == player)
+                .map(|(w, _)| w),
+        };
+        let Some(workspace) = workspace else {
+            client_error(&mut client, format!("{} does not have a workspace", player));
+            continue;
+        };
+        position.0 = plots.player_spawn(workspace.plot);
+        client_info(
+            &mut client,
+            format!("visiting '{}' by {}", workspace.name, workspace.owner),
+        );
+    }
+}
+
+fn handle_bounds_command(
+    mut events: EventReader<CommandResultEvent<BoundsCommand>>,
+    mut workspaces: Query<(
+        &Workspace,
+        &mut Bounds,
+        &mut Section,
+        &Occupancy,
+        &mut Dirty,
+    )>,
+    layer: Query<&ChunkLayer, With<EditorLayer>>,
+    mut sender: Query<(&mut Client, &Editing)>,
+    plots: Res<Plots>,
+) {
+    let Ok(layer) = layer.get_single() else {
+        return;
+    };
+    for event in events.read() {
+        let (mut client, editing) = match sender.get_mut(event.executor) {
+            Ok(c) => c,
+            Err(_) => continue,
+        };
+        let Ok((workspace, mut bounds, mut section, occupancy, mut dirty)) =
+            workspaces.get_mut(editing.0)
+        else {
+            continue;
+        };
+        let (direction, amount) = match &event.result {
+            BoundsCommand::Lock => {
+                bounds.locked = true;
+                client_info(&mut client, "locked bounds".into());
+                continue;
+            }
+            BoundsCommand::Unlock => {
+                bounds.locked = false;
+                let old = (section.position, section.size);
+                bounds.update(&mut section, occupancy);
+                if old != (section.position, section.size) {
+                    dirty.set_if_neq(Dirty(true));
+                }
+                client_info(&mut client, "unlocked bounds".into());
+                continue;
+            }
+            BoundsCommand::Expand { amount, .. } | BoundsCommand::Contract { amount, .. }
+                if *amount <= 0 =>
+            {
+                client_error(&mut client, "the amount has to be at least 1".into());
+                continue;
+            }
+            BoundsCommand::Expand { direction, amount } => (direction, *amount),
+            BoundsCommand::Contract { direction, amount } => (direction, -*amount),
+            // handled by `handle_bounds_style_command`
+            _ => continue,
+        };
+        let Some(direction) = parse_direction(direction) else {
+            client_error(&mut client, format!("unknown direction '{}'", direction));
+            continue;
+        };
+        let Some((position, size)) = resize(section.position, section.size, direction, amount)
+        else {
+            client_error(&mut client, "the bounds would be too large".into());
+            continue;
+        };
+        if size.cmplt(IVec3::ONE).any() || !occupancy.fits_in(position, size) {
+            client_error(
+                &mut client,
+                "the bounds can not be smaller than the structure".into(),
+            );
+            continue;
+        }
+        // bounds outside the plot would cover other workspaces, and larger ones could not be saved
+        if let Err(e) = workspace::check_fits(layer, plots.center(workspace.plot), (position, size))
+            .and_then(|_| palette::check_size(size))
+        {
+            client_error(&mut client, format!("could not resize the bounds, {}", e));
+            continue;
+        }
+        section.position = position;
+        section.size = size;
+        bounds.locked = true;
+        dirty.set_if_neq(Dirty(true));
+        client_info(
+            &mut client,
+            format!(
+                "resized bounds to {}x{}x{}, the bounds are now locked",
+                size.x, size.y, size.z
+            ),
+        );
+    }
+}
+
+fn handle_bounds_style_command(
+    mut events: EventReader<CommandResultEvent<BoundsCommand>>,
+    mut workspaces: Query<(&Workspace, &mut Section)>,
+    mut sender: Query<(&mut Client, &Editing)>,
+    mut config: ResMut<Config>,
+) {
+    for event in events.read() {
+        let (mut client, editing) = match sender.get_mut(event.executor) {
+            Ok(c) => c,
+            Err(_) => continue,
+        };
+        let Ok((workspace, mut section)) = workspaces.get_mut(editing.0) else {
+            continue;
+        };
+        let mut style = SectionStyle::from_section(&section);
+        match &event.result {
+            BoundsCommand::StyleWall { block } | BoundsCommand::StyleCorner { block } => {
+                let Some(kind) = BlockKind::from_str(block.trim_start_matches("minecraft:")) else {
+                    client_error(&mut client, format!("unknown block '{}'", block));
+                    continue;
+                };
+                let name = kind.to_str().to_string();
+                match event.result {
+                    BoundsCommand::StyleWall { .. } => style.wall_block = name,
+                    _ => style.corner_block = name,
+                }
+            }
+            BoundsCommand::StyleWallWidth { width: size }
+            | BoundsCommand::StyleCornerSize { size } => {
+                if !(0.0..=1.0).contains(size) {
+                    client_error(&mut client, "the size must be between 0 and 1".into());
+                    continue;
+                }
+                match event.result {
+                    BoundsCommand::StyleWallWidth { .. } => style.wall_width = *size,
+                    _ => style.corner_size = *size,
+                }
+            }
+            BoundsCommand::StyleGlow { part, color } => {
+                // `off` stops glowing, `on` glows white
+                let (glowing, color) = match color.as_str() {
+                    "off" => (false, None),
+                    "on" => (true, None),
+                    name => match section::parse_color(name) {
+                        Some(_) => (true, Some(name.to_lowercase())),
+                        None => {
+                            client_error(&mut client, format!("unknown color '{}'", name));
+                            continue;
+                        }
+                    },
+                };
+                match part.as_str() {
+                    "walls" | "wall" => {
+                        style.glowing_walls = glowing;
+                        style.wall_color = color;
+                    }
+                    "corners" | "corner" => {
+                        style.glowing_corners = glowing;
+                        style.corner_color = color;
+                    }
+                    _ => {
+                        client_error(
+                            &mut client,
+                            format!("unknown part '{}', use walls or corners", part),
+                        );
+                        continue;
+                    }
+                }
+            }
+            BoundsCommand::StyleReset => style = config.default_style.clone(),
+            _ => continue,
+        }
+        style.apply(&mut section);
+        if style == config.default_style {
+            config.styles.remove(&workspace.name);
+        } else {
+            config.styles.insert(workspace.name.clone(), style);
+        }
+        // the config is only written if it was loaded from a file
+        if let Some(path) = config.path.clone() {
+            if let Err(e) = config.save(&path) {
+                client_error(&mut client, e);
                 continue;
             }
+        }
+        client_info(
+            &mut client,
+            format!("changed the style of the bounds of '{}'", workspace.name),
+        );
+    }
+}
+
+fn handle_void_command(
+    mut events: EventReader<CommandResultEvent<VoidCommand>>,
+    mut workspaces: Query<(&Bounds, &mut Section, &mut Occupancy, &mut Dirty)>,
+    mut layer: Query<&mut ChunkLayer, With<EditorLayer>>,
+    mut sender: Query<(&mut Client, &Editing)>,
+) {
+    let Ok(mut layer) = layer.get_single_mut() else {
+        return;
+    };
+    for event in events.read() {
+        let (mut client, editing) = match sender.get_mut(event.executor) {
+            Ok(c) => c,
+            Err(_) => continue,
+        };
+        let Ok((bounds, mut section, mut occupancy, mut dirty)) = workspaces.get_mut(editing.0)
+        else {
+            continue;
         };
-        let structure = match Structure::deserialize(&data) {
+        let (from, to) = match event.result {
+            VoidCommand::Fill => (BlockState::AIR, STRUCTURE_VOID),
+            VoidCommand::Clear => (STRUCTURE_VOID, BlockState::AIR),
+        };
+        let mut count = 0;
+        for x in 0..section.size.x {
+            for y in 0..section.size.y {
+                for z in 0..section.size.z {
+                    let pos = section.position + IVec3::new(x, y, z);
+                    if layer.block(pos).map(|block| block.state) == Some(from) {
+                        occupancy.set_block(&mut layer, pos, to);
+                        count += 1;
+                    }
+                }
+            }
+        }
+        bounds.update(&mut section, &occupancy);
+        if count > 0 {
+            dirty.set_if_neq(Dirty(true));
+        }
+        client_info(&mut client, format!("replaced {} blocks", count));
+    }
+}
+
+fn handle_paste_command(
+    mut events: EventReader<CommandResultEvent<PasteCommand>>,
+    origins: Query<&Origin>,
+    mut workspaces: Query<(
+        &Workspace,
+        &Bounds,
+        &mut Section,
+        &mut Occupancy,
+        &mut Dirty,
+    )>,
+    mut layer: Query<&mut ChunkLayer, With<EditorLayer>>,
+    mut sender: Query<(&mut Client, &Editing)>,
+    plots: Res<Plots>,
+) {
+    let Ok(mut layer) = layer.get_single_mut() else {
+        return;
+    };
+    for event in events.read() {
+        let (mut client, editing) = match sender.get_mut(event.executor) {
+            Ok(c) => c,
+            Err(_) => continue,
+        };
+        let Ok((workspace, bounds, mut section, mut occupancy, mut dirty)) =
+            workspaces.get_mut(editing.0)
+        else {
+            continue;
+        };
+        let Ok(origin) = origins.get(workspace.origin) else {
+            continue;
+        };
+        let path = string_to_path_buf(&event.result.path.0);
+        let structure = match save::read_structure(&path) {
             Ok(s) => s,
             Err(e) => {
-                client_error(
-                    &mut client,
-                    format!("error while trying serialize structure: {}", e),
-                );
+                client_error(&mut client, e);
                 continue;
             }
         };
-        load_structure(&mut origin, &mut section, &mut layer, &structure);
+        let (position, size) = union(
+            (section.position, section.size),
+            (origin.position - structure.origin_pos, structure.size),
+        );
+        if let Err(e) =
+            workspace::check_fits(&layer, plots.center(workspace.plot), (position, size))
+        {
+            client_error(
+                &mut client,
+                format!("could not paste '{}', {}", path.display(), e),
+            );
+            continue;
+        }
+        void::paste(&structure, &mut layer, origin.position);
+        *occupancy = Occupancy::from_layer(&layer, position, size);
+        bounds.update(&mut section, &occupancy);
+        dirty.set_if_neq(Dirty(true));
         client_info(
             &mut client,
-            format!("loaded structure '{}'", path.display()),
+            format!("pasted structure '{}' at the origin", path.display()),
         );
-        current_path.0 = Some(path);
     }
 }
 
-fn load_structure(
-    origin: &mut Origin,
-    section: &mut Section,
-    layer: &mut ChunkLayer,
-    structure: &Structure,
+fn handle_stats_command(
+    mut events: EventReader<CommandResultEvent<StatsCommand>>,
+    workspaces: Query<(&Workspace, &Section)>,
+    layer: Query<&ChunkLayer, With<EditorLayer>>,
+    mut sender: Query<(&mut Client, &Editing)>,
 ) {
-    clear(layer, section);
-    origin.position = BlockPos::new(0, super::SPAWN_Y, 0);
-    structure.render_to_layer(layer, origin.position);
-    section.size = structure.size;
-    section.position = origin.position - structure.origin_pos;
+    let Ok(layer) = layer.get_single() else {
+        return;
+    };
+    for event in events.read() {
+        let (mut client, editing) = match sender.get_mut(event.executor) {
+            Ok(c) => c,
+            Err(_) => continue,
+        };
+        let Ok((workspace, section)) = workspaces.get(editing.0) else {
+            continue;
+        };
+        let size = section.size;
+        let counts = stats::block_counts(layer, section.position, size);
+        let blocks: u64 = counts.iter().map(|(_, count)| count).sum();
+        let mut message = format!(
+            "'{}' is {}x{}x{} ({} blocks of space) with {} blocks",
+            workspace.name,
+            size.x,
+            size.y,
+            size.z,
+            size.x as i64 * size.y as i64 * size.z as i64,
+            blocks
+        );
+        for (kind, count) in counts.iter().take(STATS_KINDS) {
+            message += &format!("\n  {} {}", count, kind.to_str());
+        }
+        if counts.len() > STATS_KINDS {
+            message += &format!("\n  and {} other kinds", counts.len() - STATS_KINDS);
+        }
+        client_info(&mut client, message);
+    }
+}
+
+fn handle_bom_command(
+    mut events: EventReader<CommandResultEvent<BomCommand>>,
+    workspaces: Query<(&Section, &CurrentPath)>,
+    layer: Query<&ChunkLayer, With<EditorLayer>>,
+    mut sender: Query<(&mut Client, &Editing)>,
+) {
+    let Ok(layer) = layer.get_single() else {
+        return;
+    };
+    for event in events.read() {
+        let (mut client, editing) = match sender.get_mut(event.executor) {
+            Ok(c) => c,
+            Err(_) => continue,
+        };
+        let Ok((section, current_path)) = workspaces.get(editing.0) else {
+            continue;
+        };
+        let Some(path) = &current_path.0 else {
+            client_error(
+                &mut client,
+                "save the structure first, the materials are saved next to it".into(),
+            );
+            continue;
+        };
+        let path = path.with_extension("csv");
+        let materials = stats::materials(layer, section.position, section.size);
+        if let Err(e) = fs::write(&path, stats::materials_csv(&materials)) {
+            client_error(
+                &mut client,
+                format!("could not write '{}': {}", path.display(), e),
+            );
+            continue;
+        }
+        let shulker_boxes = stats::shulker_boxes(&materials);
+        client_info(
+            &mut client,
+            format!(
+                "saved {} materials to '{}', they fit in {} shulker boxes",
+                materials.len(),
+                path.display(),
+                shulker_boxes
+            ),
+        );
+    }
 }
 
-fn clear(layer: &mut ChunkLayer, section: &mut Section) {
-    for x in 0..section.size.x {
-        for y in 0..section.size.y {
-            for z in 0..section.size.z {
-                let pos = section.position + IVec3::new(x, y, z);
-                layer.set_block(pos, BlockState::AIR);
+fn handle_grid_command(
+    mut commands: Commands,
+    mut events: EventReader<CommandResultEvent<GridCommand>>,
+    workspaces: Query<&Workspace>,
+    mut sender: Query<(&mut Client, &Editing)>,
+) {
+    for event in events.read() {
+        let (mut client, editing) = match sender.get_mut(event.executor) {
+            Ok(c) => c,
+            Err(_) => continue,
+        };
+        let Ok(workspace) = workspaces.get(editing.0) else {
+            continue;
+        };
+        match event.result {
+            GridCommand::On { spacing } => {
+                let spacing = spacing.unwrap_or(grid::DEFAULT_SPACING);
+                if spacing < 1 {
+                    client_error(&mut client, "spacing must be at least 1".to_string());
+                    continue;
+                }
+                commands.entity(editing.0).insert(Grid::new(spacing));
+                client_info(
+                    &mut client,
+                    format!(
+                        "showing a grid every {} blocks in '{}'",
+                        spacing, workspace.name
+                    ),
+                );
+            }
+            GridCommand::Off => {
+                commands.entity(editing.0).remove::<Grid>();
+                client_info(&mut client, format!("hid the grid in '{}'", workspace.name));
             }
         }
     }
 }
 
-fn handle_path_command(
-    mut events: EventReader<CommandResultEvent<PathCommand>>,
-    mut sender: Query<&mut Client>,
-    current_path: Res<CurrentPath>,
+fn handle_watch_command(
+    mut commands: Commands,
+    mut events: EventReader<CommandResultEvent<WatchCommand>>,
+    workspaces: Query<(&Workspace, &CurrentPath)>,
+    mut sender: Query<(&mut Client, &Editing)>,
 ) {
     for event in events.read() {
-        let message = if let Some(path) = &current_path.0 {
-            format!("current path: '{}'", path.display())
-        } else {
-            "no path selected".into()
+        let (mut client, editing) = match sender.get_mut(event.executor) {
+            Ok(c) => c,
+            Err(_) => continue,
         };
-        let _ = sender
-            .get_mut(event.executor)
-            .map(|mut client| client_info(&mut client, message));
+        let Ok((workspace, current_path)) = workspaces.get(editing.0) else {
+            continue;
+        };
+        match event.result {
+            WatchCommand::On => {
+                let Some(path) = &current_path.0 else {
+                    client_error(
+                        &mut client,
+                        format!("'{}' has no file to watch, save it first", workspace.name),
+                    );
+                    continue;
+                };
+                let mut watch = Watch::default();
+                watch.record(path);
+                commands.entity(editing.0).insert(watch);
+                client_info(
+                    &mut client,
+                    format!("reloading '{}' when it changes", path.display()),
+                );
+            }
+            WatchCommand::Off => {
+                commands.entity(editing.0).remove::<Watch>();
+                client_info(
+                    &mut client,
+                    format!("stopped watching '{}'", workspace.name),
+                );
+            }
+        }
     }
 }
 
-fn handle_new_command(
-    mut events: EventReader<CommandResultEvent<NewCommand>>,
-    mut origin: Query<&mut Origin>,
-    mut section: Query<&mut Section, With<Bounds>>,
-    mut layer: Query<&mut ChunkLayer>,
-    mut sender: Query<&mut Client>,
-    mut current_path: ResMut<CurrentPath>,
+/// Highlights the blocks that differ from a file, or hides the highlights without a path
+fn handle_diff_command(
+    mut commands: Commands,
+    mut events: EventReader<CommandResultEvent<DiffCommand>>,
+    origins: Query<&Origin>,
+    workspaces: Query<(&Workspace, &Section)>,
+    markers: Query<(Entity, &DiffMarker)>,
+    layer: Query<&ChunkLayer, With<EditorLayer>>,
+    mut sender: Query<(&mut Client, &Editing)>,
 ) {
-    let mut origin = origin.single_mut();
-    let mut section = section.single_mut();
-    let mut layer = layer.single_mut();
+    let Ok(layer) = layer.get_single() else {
+        return;
+    };
     for event in events.read() {
-        let mut client = match sender.get_mut(event.executor) {
+        let (mut client, editing) = match sender.get_mut(event.executor) {
             Ok(c) => c,
             Err(_) => continue,
         };
-        let structure = Structure {
-            size: IVec3::new(1, 1, 1),
-            origin_pos: IVec3::ZERO,
-            blocks: vec![BlockState::BEDROCK],
+        let Ok((workspace, section)) = workspaces.get(editing.0) else {
+            continue;
         };
-        load_structure(&mut origin, &mut section, &mut layer, &structure);
-        client_info(&mut client, "created new structure".into());
-        current_path.0 = None;
+        let Ok(origin) = origins.get(workspace.origin) else {
+            continue;
+        };
+        diff::despawn_markers(&mut commands, &markers, editing.0);
+        let Some(path) = &event.result.path else {
+            client_info(
+                &mut client,
+                format!("hid the differences in '{}'", workspace.name),
+            );
+            continue;
+        };
+        let path = string_to_path_buf(&path.0);
+        let other = match save::read_structure(&path) {
+            Ok(s) => s,
+            Err(e) => {
+                client_error(&mut client, e);
+                continue;
+            }
+        };
+        let structure =
+            Structure::from_section(layer, section.position, section.size, origin.position);
+        let changes = diff::diff(&other, &structure);
+        let (mut added, mut removed, mut changed) = (0, 0, 0);
+        for change in &changes {
+            match change.kind() {
+                ChangeKind::Added => added += 1,
+                ChangeKind::Removed => removed += 1,
+                ChangeKind::Changed => changed += 1,
+            }
+        }
+        for change in changes.iter().take(diff::MAX_MARKERS) {
+            diff::spawn_marker(
+                &mut commands,
+                section.layer,
+                editing.0,
+                origin.position + change.pos,
+                Some(change.kind()),
+            );
+        }
+        let mut message = format!(
+            "compared to '{}': {} added (green), {} removed (red), {} changed (yellow)",
+            path.display(),
+            added,
+            removed,
+            changed
+        );
+        if changes.len() > diff::MAX_MARKERS {
+            message += &format!(", only the first {} are highlighted", diff::MAX_MARKERS);
+        }
+        message += ", use '/diff' to hide them";
+        client_info(&mut client, message);
+    }
+}
+
+/// Applies the changes made to a file since the current file to the workspace,
+/// blocks that were changed in both are left as they are and highlighted
+fn handle_merge_command(
+    mut commands: Commands,
+    mut events: EventReader<CommandResultEvent<MergeCommand>>,
+    origins: Query<&Origin>,
+    mut workspaces: Query<(
+        &Workspace,
+        &CurrentPath,
+        &Bounds,
+        &mut Section,
+        &mut Occupancy,
+        &mut Dirty,
+    )>,
+    markers: Query<(Entity, &DiffMarker)>,
+    mut layer: Query<&mut ChunkLayer, With<EditorLayer>>,
+    mut sender: Query<(&mut Client, &Editing)>,
+    plots: Res<Plots>,
+) {
+    let Ok(mut layer) = layer.get_single_mut() else {
+        return;
+    };
+    for event in events.read() {
+        let (mut client, editing) = match sender.get_mut(event.executor) {
+            Ok(c) => c,
+            Err(_) => continue,
+        };
+        let Ok((workspace, current_path, bounds, mut section, mut occupancy, mut dirty)) =
+            workspaces.get_mut(editing.0)
+        else {
+            continue;
+        };
+        let Ok(origin) = origins.get(workspace.origin) else {
+            continue;
+        };
+        let Some(base_path) = &current_path.0 else {
+            client_error(
+                &mut client,
+                "save the structure first, changes are merged relative to its file".into(),
+            );
+            continue;
+        };
+        let path = string_to_path_buf(&event.result.path.0);
+        if &path == base_path {
+            client_error(
+                &mut client,
+                format!("'{}' is the current file of the workspace", path.display()),
+            );
+            continue;
+        }
+        let (base, theirs) = match (save::read_structure(base_path), save::read_structure(&path)) {
+            (Ok(base), Ok(theirs)) => (base, theirs),
+            (Err(e), _) | (_, Err(e)) => {
+                client_error(&mut client, e);
+                continue;
+            }
+        };
+        // changes are within our blocks or theirs, so all of them have to fit in the plot
+        let area = union(
+            (section.position, section.size),
+            (origin.position - theirs.origin_pos, theirs.size),
+        );
+        if let Err(e) = workspace::check_fits(&layer, plots.center(workspace.plot), area) {
+            client_error(
+                &mut client,
+                format!("could not merge '{}', {}", path.display(), e),
+            );
+            continue;
+        }
+        let ours = Structure::from_section(&layer, section.position, section.size, origin.position);
+        let merge = diff::merge(&base, &ours, &theirs);
+
+        for (pos, block) in &merge.changes {
+            occupancy.set_block(&mut layer, origin.position + *pos, *block);
+        }
+        bounds.update(&mut section, &occupancy);
+        if !merge.changes.is_empty() {
+            dirty.set_if_neq(Dirty(true));
+        }
+
+        diff::despawn_markers(&mut commands, &markers, editing.0);
+        for pos in merge.conflicts.iter().take(diff::MAX_MARKERS) {
+            diff::spawn_marker(
+                &mut commands,
+                section.layer,
+                editing.0,
+                origin.position + *pos,
+                None,
+            );
+        }
+        let mut message = format!(
+            "merged {} changes from '{}'",
+            merge.changes.len(),
+            path.display()
+        );
+        if !merge.conflicts.is_empty() {
+            message += &format!(
+                ", {} blocks were changed in both and kept as they are (purple), use '/diff' to hide them",
+                merge.conflicts.len()
+            );
+        }
+        client_info(&mut client, message);
+    }
+}
+
+fn handle_snapshot_command(
+    mut events: EventReader<CommandResultEvent<SnapshotCommand>>,
+    origins: Query<&Origin>,
+    workspaces: Query<(&Workspace, &Section, &CurrentPath)>,
+    layer: Query<&ChunkLayer, With<EditorLayer>>,
+    mut sender: Query<(&mut Client, &Editing)>,
+) {
+    let Ok(layer) = layer.get_single() else {
+        return;
+    };
+    for event in events.read() {
+        let (mut client, editing) = match sender.get_mut(event.executor) {
+            Ok(c) => c,
+            Err(_) => continue,
+        };
+        let Ok((workspace, section, current_path)) = workspaces.get(editing.0) else {
+            continue;
+        };
+        let Ok(origin) = origins.get(workspace.origin) else {
+            continue;
+        };
+        let Some(current_path) = &current_path.0 else {
+            client_error(
+                &mut client,
+                "save the structure first, snapshots are saved next to it".into(),
+            );
+            continue;
+        };
+        let (name, force) = strip_flag(&event.result.name.0, "force");
+        let path = match snapshot::path(current_path, name) {
+            Ok(path) => path,
+            Err(e) => {
+                client_error(&mut client, e);
+                continue;
+            }
+        };
+        if path.exists() && !force {
+            client_error(
+                &mut client,
+                format!(
+                    "there already is a snapshot '{}', use '/snapshot {} force' to replace it",
+                    name, name
+                ),
+            );
+            continue;
+        }
+        let structure =
+            Structure::from_section(layer, section.position, section.size, origin.position);
+        if let Err(e) = save::save_structure(&path, &structure) {
+            client_error(
+                &mut client,
+                format!("could not save snapshot '{}', {}", name, e),
+            );
+            continue;
+        }
+        client_info(
+            &mut client,
+            format!(
+                "saved snapshot '{}', use '/restore {}' to go back to it",
+                name, name
+            ),
+        );
+    }
+}
+
+fn handle_snapshots_command(
+    mut events: EventReader<CommandResultEvent<SnapshotsCommand>>,
+    workspaces: Query<(&Workspace, &CurrentPath)>,
+    mut sender: Query<(&mut Client, &Editing)>,
+) {
+    for event in events.read() {
+        let (mut client, editing) = match sender.get_mut(event.executor) {
+            Ok(c) => c,
+            Err(_) => continue,
+        };
+        let Ok((workspace, current_path)) = workspaces.get(editing.0) else {
+            continue;
+        };
+        let Some(current_path) = &current_path.0 else {
+            client_error(
+                &mut client,
+                format!("'{}' has no snapshots, it is not saved", workspace.name),
+            );
+            continue;
+        };
+        match snapshot::list(current_path) {
+            Ok(names) if names.is_empty() => client_info(
+                &mut client,
+                format!(
+                    "'{}' has no snapshots, use '/snapshot <name>' to save one",
+                    workspace.name
+                ),
+            ),
+            Ok(names) => client_info(
+                &mut client,
+                format!(
+                    "snapshots of '{}', oldest first:\n  {}",
+                    workspace.name,
+                    names.join("\n  ")
+                ),
+            ),
+            Err(e) => client_error(&mut client, format!("could not list the snapshots: {}", e)),
+        }
+    }
+}
+
+fn handle_restore_command(
+    mut events: EventReader<CommandResultEvent<RestoreCommand>>,
+    mut origins: Query<&mut Origin>,
+    mut workspaces: Query<(
+        &Workspace,
+        &mut Bounds,
+        &mut Section,
+        &mut Occupancy,
+        &CurrentPath,
+        &mut Dirty,
+    )>,
+    mut layer: Query<&mut ChunkLayer, With<EditorLayer>>,
+    mut sender: Query<(&mut Client, &Editing)>,
+    plots: Res<Plots>,
+) {
+    let Ok(mut layer) = layer.get_single_mut() else {
+        return;
+    };
+    for event in events.read() {
+        let (mut client, editing) = match sender.get_mut(event.executor) {
+            Ok(c) => c,
+            Err(_) => continue,
+        };
+        let Ok((workspace, mut bounds, mut section, mut occupancy, current_path, mut dirty)) =
+            workspaces.get_mut(editing.0)
+        else {
+            continue;
+        };
+        let Ok(mut origin) = origins.get_mut(workspace.origin) else {
+            continue;
+        };
+        let Some(current_path) = &current_path.0 else {
+            client_error(
+                &mut client,
+                format!("'{}' has no snapshots, it is not saved", workspace.name),
+            );
+            continue;
+        };
+        let (name, force) = strip_flag(&event.result.name.0, "force");
+        if dirty.0 && !force {
+            client_error(
+                &mut client,
+                format!(
+                    "there are unsaved changes, use '/restore {} force' to discard them",
+                    name
+                ),
+            );
+            continue;
+        }
+        let path = match snapshot::path(current_path, name) {
+            Ok(path) => path,
+            Err(e) => {
+                client_error(&mut client, e);
+                continue;
+            }
+        };
+        if !path.exists() {
+            client_error(
+                &mut client,
+                format!(
+                    "there is no snapshot '{}', use '/snapshots' to list them",
+                    name
+                ),
+            );
+            continue;
+        }
+        let structure = match save::read_structure(&path) {
+            Ok(s) => s,
+            Err(e) => {
+                client_error(&mut client, e);
+                continue;
+            }
+        };
+        if let Err(e) = load_structure(
+            &mut origin,
+            &mut bounds,
+            &mut section,
+            &mut occupancy,
+            &mut layer,
+            &structure,
+            plots.center(workspace.plot),
+        ) {
+            client_error(
+                &mut client,
+                format!("could not restore snapshot '{}', {}", name, e),
+            );
+            continue;
+        }
+        // the current file stays the same, so the restored blocks are not saved yet
+        dirty.set_if_neq(Dirty(true));
+        client_info(
+            &mut client,
+            format!("restored snapshot '{}', save to keep it", name),
+        );
+    }
+}
+
+/// Tells the players editing a workspace when it gets unsaved changes
+fn notify_unsaved_changes(
+    workspaces: Query<(Entity, &Workspace, &Dirty), Changed<Dirty>>,
+    mut editors: Query<(&mut Client, &Editing)>,
+) {
+    for (entity, workspace, dirty) in &workspaces {
+        if !dirty.0 {
+            continue;
+        }
+        for (mut client, editing) in &mut editors {
+            if editing.0 == entity {
+                client_info(
+                    &mut client,
+                    format!("'{}' has unsaved changes", workspace.name),
+                );
+            }
+        }
+    }
+}
+
+/// Moves the face of the area in the given direction by `amount` blocks,
+/// `None` if a coordinate of the new area does not fit in an `i32`
+fn resize(
+    position: BlockPos,
+    size: IVec3,
+    direction: Direction,
+    amount: i32,
+) -> Option<(BlockPos, IVec3)> {
+    // the axis of the face, and whether it is the face at the start of the area
+    let (axis, moves_start) = match direction {
+        Direction::Down => (1, true),
+        Direction::Up => (1, false),
+        Direction::North => (2, true),
+        Direction::South => (2, false),
+        Direction::West => (0, true),
+        Direction::East => (0, false),
+    };
+    let mut start = [position.x, position.y, position.z];
+    let mut size = size.to_array();
+    size[axis] = size[axis].checked_add(amount)?;
+    if moves_start {
+        start[axis] = start[axis].checked_sub(amount)?;
+    }
+    start[axis].checked_add(size[axis])?;
+    Some((
+        BlockPos::new(start[0], start[1], start[2]),
+        IVec3::from_array(size),
+    ))
+}
+
+fn parse_direction(string: &str) -> Option<Direction> {
+    Some(match string {
+        "up" | "u" => Direction::Up,
+        "down" | "d" => Direction::Down,
+        "north" | "n" => Direction::North,
+        "south" | "s" => Direction::South,
+        "east" | "e" => Direction::East,
+        "west" | "w" => Direction::West,
+        _ => return None,
+    })
+}
+
+/// Checks an optional word argument, which must be `flag` if given
+fn parse_flag(arg: &Option<String>, flag: &str) -> Result<bool, String> {
+    match arg {
+        None => Ok(false),
+        Some(arg) if arg == flag => Ok(true),
+        Some(arg) => Err(format!("expected '{}', got '{}'", flag, arg)),
+    }
+}
+
+/// Splits a trailing flag like `force` off a greedy argument
+fn strip_flag<'a>(arg: &'a str, flag: &str) -> (&'a str, bool) {
+    match arg.strip_suffix(flag) {
+        Some(rest) if rest.ends_with(' ') => (rest.trim_end(), true),
+        _ => (arg, false),
     }
 }
 
+/// Adds `.vstruc` to paths without the extension of a known format
 fn string_to_path_buf(string: &str) -> PathBuf {
     let mut buf = Path::new(string).to_path_buf();
-    if buf.extension().map(|ext| ext == "vstruc").unwrap_or(false) {
+    if save::Format::from_path(&buf).is_some() {
         buf
     } else {
         buf.set_extension("vstruc");
@@ -308,7 +1769,7 @@ fn string_to_path_buf(string: &str) -> PathBuf {
     }
 }
 
-fn client_error(client: &mut Client, message: String) {
+pub(crate) fn client_error(client: &mut Client, message: String) {
     client.send_chat_message(
         "[Error] "
             .color(NamedColor::DarkRed)
@@ -317,7 +1778,7 @@ fn client_error(client: &mut Client, message: String) {
     );
 }
 
-fn client_info(client: &mut Client, message: String) {
+pub(crate) fn client_info(client: &mut Client, message: String) {
     client.send_chat_message(
         "[Info] "
             .color(NamedColor::White)
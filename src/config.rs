@@ -0,0 +1,224 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use valence::prelude::*;
+
+use crate::section::{self, Section};
+
+pub const CONFIG_PATH: &str = "valence_editor.json";
+
+/// What a player is allowed to do
+#[derive(Component, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    /// can look at and visit workspaces
+    Viewer,
+    /// can edit their own workspaces
+    Builder,
+    /// can edit every workspace and use every command
+    Admin,
+}
+
+impl Role {
+    /// The scope given to players with this role
+    pub fn scope(self) -> &'static str {
+        match self {
+            Role::Viewer => "valence_editor.viewer",
+            Role::Builder => "valence_editor.builder",
+            Role::Admin => "valence.admin",
+        }
+    }
+
+    /// The command scopes linked to the scope of the role
+    pub fn commands(self) -> &'static [&'static str] {
+        match self {
+            Role::Viewer => &["valence.command.path", "valence.command.visit"],
+            Role::Builder => &[
+                "valence.command.path",
+                "valence.command.visit",
+                "valence.command.save",
+                "valence.command.load",
+                "valence.command.new",
+                "valence.command.origin",
+                "valence.command.bounds",
+                "valence.command.void",
+                "valence.command.paste",
+                "valence.command.open",
+                "valence.command.close",
+                "valence.command.switch",
+                "valence.command.stats",
+                "valence.command.bom",
+                "valence.command.grid",
+                "valence.command.watch",
+                "valence.command.diff",
+                "valence.command.merge",
+                "valence.command.snapshot",
+                "valence.command.snapshots",
+                "valence.command.restore",
+            ],
+            // already linked to every command
+            Role::Admin => &[],
+        }
+    }
+
+    pub fn op_level(self) -> u8 {
+        match self {
+            Role::Viewer => 0,
+            Role::Builder => 2,
+            Role::Admin => 4,
+        }
+    }
+
+    pub fn game_mode(self) -> GameMode {
+        match self {
+            Role::Viewer => GameMode::Spectator,
+            Role::Builder | Role::Admin => GameMode::Creative,
+        }
+    }
+}
+
+#[derive(Resource, Serialize, Deserialize)]
+pub struct Config {
+    /// verify players with mojang, which is needed for usernames and uuids to be trusted
+    pub online_mode: bool,
+    /// role of players that are not in `roles`
+    pub default_role: Role,
+    /// roles by username or uuid
+    pub roles: HashMap<String, Role>,
+    /// port of the http api on localhost, the api is off if it is not set
+    #[serde(default)]
+    pub api_port: Option<u16>,
+    /// look of the bounds of workspaces without their own style
+    #[serde(default)]
+    pub default_style: SectionStyle,
+    /// look of the bounds by workspace name, changed with `/bounds style`
+    #[serde(default)]
+    pub styles: HashMap<String, SectionStyle>,
+    /// where the config was loaded from, changes are written back to it
+    #[serde(skip)]
+    pub path: Option<PathBuf>,
+}
+
+/// The look of the bounds, blocks are block names and colors are chat color names
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct SectionStyle {
+    pub wall_block: String,
+    pub corner_block: String,
+    pub wall_width: f32,
+    pub corner_size: f32,
+    pub glowing_walls: bool,
+    pub glowing_corners: bool,
+    /// glow color of the walls, white if not set
+    pub wall_color: Option<String>,
+    /// glow color of the corners, white if not set
+    pub corner_color: Option<String>,
+}
+
+impl Default for SectionStyle {
+    fn default() -> Self {
+        Self {
+            wall_block: "lime_concrete".into(),
+            corner_block: "lime_concrete".into(),
+            wall_width: 0.15,
+            corner_size: 0.15,
+            glowing_walls: false,
+            glowing_corners: false,
+            wall_color: None,
+            corner_color: None,
+        }
+    }
+}
+
+impl SectionStyle {
+    pub fn from_section(section: &Section) -> Self {
+        Self {
+            wall_block: section.wall_block.to_kind().to_str().into(),
+            corner_block: section.corner_block.to_kind().to_str().into(),
+            wall_width: section.wall_width,
+            corner_size: section.corner_size,
+            glowing_walls: section.glowing_walls,
+            glowing_corners: section.glowing_corners,
+            wall_color: section
+                .wall_color
+                .map(|color| section::color_name(color).into()),
+            corner_color: section
+                .corner_color
+                .map(|color| section::color_name(color).into()),
+        }
+    }
+
+    /// Sets the style of the section, unknown blocks and colors are left as they were
+    pub fn apply(&self, section: &mut Section) {
+        if let Some(kind) = BlockKind::from_str(&self.wall_block) {
+            section.wall_block = kind.to_state();
+        }
+        if let Some(kind) = BlockKind::from_str(&self.corner_block) {
+            section.corner_block = kind.to_state();
+        }
+        section.wall_width = self.wall_width;
+        section.corner_size = self.corner_size;
+        section.glowing_walls = self.glowing_walls;
+        section.glowing_corners = self.glowing_corners;
+        section.wall_color = self.wall_color.as_deref().and_then(section::parse_color);
+        section.corner_color = self.corner_color.as_deref().and_then(section::parse_color);
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            online_mode: true,
+            default_role: Role::Builder,
+            roles: HashMap::new(),
+            api_port: None,
+            default_style: SectionStyle::default(),
+            styles: HashMap::new(),
+            path: None,
+        }
+    }
+}
+
+impl Config {
+    /// Reads the config, writing the default config if the file does not exist
+    pub fn load(path: &Path) -> Result<Self, String> {
+        if !path.exists() {
+            let config = Self {
+                path: Some(path.to_path_buf()),
+                ..Default::default()
+            };
+            config.save(path)?;
+            return Ok(config);
+        }
+        let data = fs::read(path).map_err(|e| format!("could not read the config: {}", e))?;
+        let config: Self =
+            serde_json::from_slice(&data).map_err(|e| format!("invalid config: {}", e))?;
+        Ok(Self {
+            path: Some(path.to_path_buf()),
+            ..config
+        })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let data = serde_json::to_vec_pretty(self)
+            .map_err(|e| format!("could not serialize the config: {}", e))?;
+        fs::write(path, data).map_err(|e| format!("could not write the config: {}", e))
+    }
+
+    /// The role of a player, roles given by uuid take priority over roles given by username
+    pub fn role(&self, username: &str, uuid: &str) -> Role {
+        self.roles
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(uuid))
+            .or_else(|| self.roles.iter().find(|(key, _)| key.as_str() == username))
+            .map(|(_, role)| *role)
+            .unwrap_or(self.default_role)
+    }
+
+    pub fn style(&self, workspace: &str) -> &SectionStyle {
+        self.styles.get(workspace).unwrap_or(&self.default_style)
+    }
+}
@@ -0,0 +1,276 @@
+use valence::{
+    entity::{
+        block_display::{self, BlockDisplayEntityBundle},
+        display::{GlowColorOverride, Scale},
+        entity::Flags,
+    },
+    math::IVec3,
+    prelude::*,
+};
+
+use crate::{palette, structure::Structure, workspace::Workspace};
+
+/// Most highlighted blocks of a single workspace, so huge diffs don't spawn too many entities
+pub const MAX_MARKERS: usize = 4096;
+const MARKER_SIZE: f32 = 0.6;
+
+pub struct DiffPlugin;
+
+impl Plugin for DiffPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, despawn_orphaned_markers);
+    }
+}
+
+/// A block that differs between two structures, positions are relative to the origin
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Change {
+    pub pos: IVec3,
+    pub old: BlockState,
+    pub new: BlockState,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+impl Change {
+    pub fn kind(&self) -> ChangeKind {
+        match (self.old == BlockState::AIR, self.new == BlockState::AIR) {
+            (true, _) => ChangeKind::Added,
+            (_, true) => ChangeKind::Removed,
+            _ => ChangeKind::Changed,
+        }
+    }
+}
+
+/// Result of merging two versions of a structure that were both changed from the same base
+pub struct Merge {
+    /// blocks to set in our version, so it contains their changes
+    pub changes: Vec<(IVec3, BlockState)>,
+    /// blocks both versions changed differently, which are left as they are in our version
+    pub conflicts: Vec<IVec3>,
+}
+
+/// Every block that differs, the structures are lined up by their origins.
+/// Blocks outside of a structure count as air.
+pub fn diff(old: &Structure, new: &Structure) -> Vec<Change> {
+    let (min, max) = extent(&[old, new]);
+    let mut changes = Vec::new();
+    for_each_pos(min, max, |pos| {
+        let (old, new) = (block_at(old, pos), block_at(new, pos));
+        if old != new {
+            changes.push(Change { pos, old, new });
+        }
+    });
+    changes
+}
+
+/// Three way merge, blocks only changed in one version take that change
+pub fn merge(base: &Structure, ours: &Structure, theirs: &Structure) -> Merge {
+    let (min, max) = extent(&[base, ours, theirs]);
+    let mut merge = Merge {
+        changes: Vec::new(),
+        conflicts: Vec::new(),
+    };
+    for_each_pos(min, max, |pos| {
+        let base = block_at(base, pos);
+        let ours = block_at(ours, pos);
+        let theirs = block_at(theirs, pos);
+        if theirs == base || theirs == ours {
+            return;
+        }
+        if ours == base {
+            merge.changes.push((pos, theirs));
+        } else {
+            merge.conflicts.push(pos);
+        }
+    });
+    merge
+}
+
+/// The block at the position relative to the origin, air if it is outside of the structure
+pub fn block_at(structure: &Structure, pos: IVec3) -> BlockState {
+    let pos = pos + structure.origin_pos;
+    if pos.cmplt(IVec3::ZERO).any() || pos.cmpge(structure.size).any() {
+        return BlockState::AIR;
+    }
+    structure.blocks[palette::block_index(structure.size, pos)]
+}
+
+/// Smallest and largest position relative to the origin within any of the structures
+fn extent(structures: &[&Structure]) -> (IVec3, IVec3) {
+    let mut min = IVec3::MAX;
+    let mut max = IVec3::MIN;
+    for structure in structures {
+        min = min.min(-structure.origin_pos);
+        max = max.max(structure.size - structure.origin_pos - 1);
+    }
+    (min, max)
+}
+
+fn for_each_pos(min: IVec3, max: IVec3, mut f: impl FnMut(IVec3)) {
+    for x in min.x..=max.x {
+        for y in min.y..=max.y {
+            for z in min.z..=max.z {
+                f(IVec3::new(x, y, z));
+            }
+        }
+    }
+}
+
+/// A glowing block highlighting a difference in a workspace
+#[derive(Component)]
+pub struct DiffMarker {
+    pub workspace: Entity,
+}
+
+/// Added blocks are green, removed blocks red, changed blocks yellow and conflicts purple
+pub fn spawn_marker(
+    commands: &mut Commands,
+    layer: EntityLayerId,
+    workspace: Entity,
+    pos: BlockPos,
+    kind: Option<ChangeKind>,
+) {
+    let (block, color) = match kind {
+        Some(ChangeKind::Added) => (BlockState::LIME_STAINED_GLASS, 0x55FF55),
+        Some(ChangeKind::Removed) => (BlockState::RED_STAINED_GLASS, 0xFF5555),
+        Some(ChangeKind::Changed) => (BlockState::YELLOW_STAINED_GLASS, 0xFFFF55),
+        None => (BlockState::PURPLE_STAINED_GLASS, 0xAA00AA),
+    };
+    let mut entity_flags = Flags::default();
+    entity_flags.set_glowing(true);
+    let offset = (1.0 - MARKER_SIZE as f64) / 2.0;
+    commands.spawn((
+        BlockDisplayEntityBundle {
+            block_display_block_state: block_display::BlockState(block),
+            display_scale: Scale(Vec3::splat(MARKER_SIZE)),
+            display_glow_color_override: GlowColorOverride(color),
+            entity_flags,
+            position: Position(DVec3::new(
+                pos.x as f64 + offset,
+                pos.y as f64 + offset,
+                pos.z as f64 + offset,
+            )),
+            layer,
+            ..Default::default()
+        },
+        DiffMarker { workspace },
+    ));
+}
+
+pub fn despawn_markers(
+    commands: &mut Commands,
+    markers: &Query<(Entity, &DiffMarker)>,
+    workspace: Entity,
+) {
+    for (marker, diff_marker) in markers {
+        if diff_marker.workspace == workspace {
+            commands.entity(marker).insert(Despawned);
+        }
+    }
+}
+
+fn despawn_orphaned_markers(
+    mut commands: Commands,
+    mut removed: RemovedComponents<Workspace>,
+    markers: Query<(Entity, &DiffMarker)>,
+) {
+    for workspace in removed.read() {
+        despawn_markers(&mut commands, &markers, workspace);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A structure with the origin in the corner, blocks are (x, y, z, block)
+    fn structure(size: IVec3, blocks: &[(i32, i32, i32, BlockState)]) -> Structure {
+        let mut structure = Structure {
+            size,
+            origin_pos: IVec3::ZERO,
+            blocks: vec![BlockState::AIR; palette::volume(size).unwrap()],
+        };
+        for (x, y, z, block) in blocks {
+            structure.blocks[palette::block_index(size, IVec3::new(*x, *y, *z))] = *block;
+        }
+        structure
+    }
+
+    #[test]
+    fn diff_finds_added_removed_and_changed_blocks() {
+        let old = structure(
+            IVec3::new(2, 1, 1),
+            &[(0, 0, 0, BlockState::STONE), (1, 0, 0, BlockState::DIRT)],
+        );
+        let new = structure(
+            IVec3::new(1, 1, 2),
+            &[
+                (0, 0, 0, BlockState::GRASS_BLOCK),
+                (0, 0, 1, BlockState::DIRT),
+            ],
+        );
+        let kinds: Vec<(IVec3, ChangeKind)> = diff(&old, &new)
+            .iter()
+            .map(|change| (change.pos, change.kind()))
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                (IVec3::new(0, 0, 0), ChangeKind::Changed),
+                (IVec3::new(0, 0, 1), ChangeKind::Added),
+                (IVec3::new(1, 0, 0), ChangeKind::Removed),
+            ]
+        );
+    }
+
+    #[test]
+    fn diff_lines_up_origins() {
+        let old = structure(IVec3::new(2, 1, 1), &[(1, 0, 0, BlockState::STONE)]);
+        let mut new = structure(IVec3::new(1, 1, 1), &[(0, 0, 0, BlockState::STONE)]);
+        new.origin_pos = IVec3::new(-1, 0, 0);
+        assert!(diff(&old, &new).is_empty());
+    }
+
+    #[test]
+    fn merge_takes_changes_from_both_sides() {
+        let size = IVec3::new(3, 1, 1);
+        let base = structure(size, &[(0, 0, 0, BlockState::STONE)]);
+        let ours = structure(
+            size,
+            &[(0, 0, 0, BlockState::STONE), (1, 0, 0, BlockState::DIRT)],
+        );
+        let theirs = structure(
+            size,
+            &[(0, 0, 0, BlockState::STONE), (2, 0, 0, BlockState::SAND)],
+        );
+        let merge = merge(&base, &ours, &theirs);
+        assert_eq!(merge.changes, vec![(IVec3::new(2, 0, 0), BlockState::SAND)]);
+        assert!(merge.conflicts.is_empty());
+    }
+
+    #[test]
+    fn merge_keeps_ours_on_conflicts() {
+        let size = IVec3::new(1, 1, 1);
+        let base = structure(size, &[(0, 0, 0, BlockState::STONE)]);
+        let ours = structure(size, &[(0, 0, 0, BlockState::DIRT)]);
+        let theirs = structure(size, &[(0, 0, 0, BlockState::SAND)]);
+        let merge = merge(&base, &ours, &theirs);
+        assert!(merge.changes.is_empty());
+        assert_eq!(merge.conflicts, vec![IVec3::ZERO]);
+    }
+
+    #[test]
+    fn merge_ignores_identical_changes() {
+        let size = IVec3::new(1, 1, 1);
+        let base = structure(size, &[]);
+        let ours = structure(size, &[(0, 0, 0, BlockState::DIRT)]);
+        let merge = merge(&base, &ours, &ours);
+        assert!(merge.changes.is_empty() && merge.conflicts.is_empty());
+    }
+}
@@ -0,0 +1,252 @@
+use valence::{
+    entity::{
+        block_display::{self, BlockDisplayEntityBundle},
+        display::Scale,
+    },
+    math::IVec3,
+    prelude::*,
+    title::SetTitle,
+};
+
+use crate::{origin::Origin, section::Section, workspace::Workspace, EditorLayer};
+
+/// Spacing of `/grid on` without an argument
+pub const DEFAULT_SPACING: i32 = 5;
+/// How far away blocks can be to show their coordinates
+const READOUT_DISTANCE: f64 = 16.0;
+const LINE_WIDTH: f32 = 0.05;
+
+pub struct GridPlugin;
+
+impl Plugin for GridPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (update_grids, remove_grids, show_coordinates));
+    }
+}
+
+/// Shows a grid on the floor of the workspace, axis lines through the origin,
+/// and the coordinates of the targeted block relative to the origin
+#[derive(Component)]
+pub struct Grid {
+    pub spacing: i32,
+    /// origin, position and size of the section the lines were drawn for
+    drawn: Option<(BlockPos, BlockPos, IVec3)>,
+}
+
+impl Grid {
+    pub fn new(spacing: i32) -> Self {
+        Self {
+            spacing,
+            drawn: None,
+        }
+    }
+}
+
+#[derive(Component)]
+struct GridLine {
+    workspace: Entity,
+}
+
+/// The last readout sent to a client, so the action bar is only sent when it changes
+#[derive(Component)]
+struct Readout {
+    relative: IVec3,
+    tick: i64,
+}
+
+fn update_grids(
+    mut commands: Commands,
+    mut workspaces: Query<(Entity, &Workspace, &Section, &mut Grid)>,
+    origins: Query<&Origin>,
+    lines: Query<(Entity, &GridLine)>,
+) {
+    for (entity, workspace, section, mut grid) in &mut workspaces {
+        let Ok(origin) = origins.get(workspace.origin) else {
+            continue;
+        };
+        let state = (origin.position, section.position, section.size);
+        if grid.drawn == Some(state) {
+            continue;
+        }
+        grid.drawn = Some(state);
+
+        despawn_lines(&mut commands, &lines, entity);
+        for (position, scale, block) in grid_lines(origin.position, section, grid.spacing) {
+            commands.spawn((
+                BlockDisplayEntityBundle {
+                    block_display_block_state: block_display::BlockState(block),
+                    display_scale: Scale(scale),
+                    position: Position(position),
+                    layer: section.layer,
+                    ..Default::default()
+                },
+                GridLine { workspace: entity },
+            ));
+        }
+    }
+}
+
+fn remove_grids(
+    mut commands: Commands,
+    mut removed: RemovedComponents<Grid>,
+    lines: Query<(Entity, &GridLine)>,
+) {
+    for workspace in removed.read() {
+        despawn_lines(&mut commands, &lines, workspace);
+    }
+}
+
+fn despawn_lines(commands: &mut Commands, lines: &Query<(Entity, &GridLine)>, workspace: Entity) {
+    for (line, grid_line) in lines {
+        if grid_line.workspace == workspace {
+            commands.entity(line).insert(Despawned);
+        }
+    }
+}
+
+/// Position, scale and block of every line
+fn grid_lines(origin: BlockPos, section: &Section, spacing: i32) -> Vec<(DVec3, Vec3, BlockState)> {
+    let start = section.position;
+    let end = section.position + section.size;
+    let floor = origin.y as f64;
+    let half = LINE_WIDTH as f64 / 2.0;
+    let mut lines = Vec::new();
+
+    // lines along z, every `spacing` blocks from the origin along x
+    for x in (start.x..=end.x).filter(|x| (x - origin.x).rem_euclid(spacing) == 0) {
+        lines.push((
+            DVec3::new(x as f64 - half, floor - half, start.z as f64),
+            Vec3::new(LINE_WIDTH, LINE_WIDTH, section.size.z as f32),
+            BlockState::LIGHT_GRAY_CONCRETE,
+        ));
+    }
+    // lines along x, every `spacing` blocks from the origin along z
+    for z in (start.z..=end.z).filter(|z| (z - origin.z).rem_euclid(spacing) == 0) {
+        lines.push((
+            DVec3::new(start.x as f64, floor - half, z as f64 - half),
+            Vec3::new(section.size.x as f32, LINE_WIDTH, LINE_WIDTH),
+            BlockState::LIGHT_GRAY_CONCRETE,
+        ));
+    }
+
+    // axis lines through the center of the origin, long enough to cross the section
+    let center = DVec3::new(
+        origin.x as f64 + 0.5,
+        origin.y as f64 + 0.5,
+        origin.z as f64 + 0.5,
+    );
+    let min = DVec3::new(
+        start.x.min(origin.x) as f64,
+        start.y.min(origin.y) as f64,
+        start.z.min(origin.z) as f64,
+    );
+    let max = DVec3::new(
+        end.x.max(origin.x + 1) as f64,
+        end.y.max(origin.y + 1) as f64,
+        end.z.max(origin.z + 1) as f64,
+    );
+    for (axis, block) in [
+        (DVec3::X, BlockState::RED_CONCRETE),
+        (DVec3::Y, BlockState::LIME_CONCRETE),
+        (DVec3::Z, BlockState::BLUE_CONCRETE),
+    ] {
+        let across = DVec3::ONE - axis;
+        let position = center * across - across * half + min * axis;
+        let scale = across * LINE_WIDTH as f64 + (max - min) * axis;
+        lines.push((position, scale.as_vec3(), block));
+    }
+    lines
+}
+
+fn show_coordinates(
+    mut commands: Commands,
+    mut clients: Query<(
+        Entity,
+        &mut Client,
+        &Position,
+        &Look,
+        &VisibleChunkLayer,
+        Option<&Readout>,
+    )>,
+    workspaces: Query<(&Workspace, &Section), With<Grid>>,
+    origins: Query<&Origin>,
+    layer: Query<(Entity, &ChunkLayer), With<EditorLayer>>,
+    server: Res<Server>,
+) {
+    let Ok((layer_entity, layer)) = layer.get_single() else {
+        return;
+    };
+    let tick = server.current_tick();
+    for (entity, mut client, position, look, visible_layer, readout) in &mut clients {
+        if visible_layer.0 != layer_entity {
+            continue;
+        }
+        let Some(target) = target_block(layer, position.0, look) else {
+            continue;
+        };
+        // coordinates are only shown for workspaces with a grid
+        let Some(origin) = workspaces
+            .iter()
+            .find(|(_, section)| contains(section, target))
+            .and_then(|(workspace, _)| origins.get(workspace.origin).ok())
+        else {
+            continue;
+        };
+        let relative = IVec3::new(
+            target.x - origin.position.x,
+            target.y - origin.position.y,
+            target.z - origin.position.z,
+        );
+        // the action bar fades, so it is sent again every second
+        if let Some(readout) = readout {
+            if readout.relative == relative && tick - readout.tick < 20 {
+                continue;
+            }
+        }
+        client.set_action_bar(format!(
+            "x: {}  y: {}  z: {}",
+            relative.x, relative.y, relative.z
+        ));
+        commands.entity(entity).insert(Readout { relative, tick });
+    }
+}
+
+fn contains(section: &Section, pos: BlockPos) -> bool {
+    let start = section.position;
+    let end = section.position + section.size;
+    (start.x..end.x).contains(&pos.x)
+        && (start.y..end.y).contains(&pos.y)
+        && (start.z..end.z).contains(&pos.z)
+}
+
+/// The first non-air block the player is looking at
+fn target_block(layer: &ChunkLayer, position: DVec3, look: &Look) -> Option<BlockPos> {
+    const EYE_HEIGHT: f64 = 1.62;
+    const STEP: f64 = 0.05;
+
+    let (yaw, pitch) = (
+        (look.yaw as f64).to_radians(),
+        (look.pitch as f64).to_radians(),
+    );
+    let direction = DVec3::new(
+        -yaw.sin() * pitch.cos(),
+        -pitch.sin(),
+        yaw.cos() * pitch.cos(),
+    );
+    let eye = position + DVec3::Y * EYE_HEIGHT;
+    (0..(READOUT_DISTANCE / STEP) as i32)
+        .map(|i| eye + direction * (i as f64 * STEP))
+        .map(|point| {
+            BlockPos::new(
+                point.x.floor() as i32,
+                point.y.floor() as i32,
+                point.z.floor() as i32,
+            )
+        })
+        .find(|pos| {
+            layer
+                .block(*pos)
+                .map(|block| block.state != BlockState::AIR)
+                .unwrap_or(false)
+        })
+}
@@ -0,0 +1,91 @@
+use serde::{Deserialize, Serialize};
+use valence::math::IVec3;
+
+use crate::{
+    palette::{self, Palette},
+    structure::Structure,
+};
+
+/// A structure as json, which is easier to read from other tools than the binary format
+#[derive(Serialize, Deserialize)]
+struct JsonStructure {
+    size: [i32; 3],
+    /// position of the origin relative to the lowest corner
+    origin: [i32; 3],
+    /// blocks like `minecraft:oak_stairs[facing=east,half=bottom]`
+    palette: Vec<String>,
+    /// index into the palette of every block, ordered by x, then y, then z
+    blocks: Vec<u32>,
+}
+
+pub fn to_json(structure: &Structure) -> Result<Vec<u8>, String> {
+    let palette = Palette::new(&structure.blocks);
+    let json = JsonStructure {
+        size: structure.size.to_array(),
+        origin: structure.origin_pos.to_array(),
+        palette: palette.names(),
+        blocks: palette.indices,
+    };
+    serde_json::to_vec(&json).map_err(|e| e.to_string())
+}
+
+pub fn from_json(data: &[u8]) -> Result<Structure, String> {
+    let json: JsonStructure =
+        serde_json::from_slice(data).map_err(|e| format!("invalid json: {}", e))?;
+    let size = IVec3::from_array(json.size);
+    let volume = palette::check_size(size)?;
+    if json.blocks.len() != volume {
+        return Err(format!(
+            "expected {} blocks for the size, got {}",
+            volume,
+            json.blocks.len()
+        ));
+    }
+    let blocks = Palette::from_names(&json.palette, json.blocks)?.blocks()?;
+    Ok(Structure {
+        size,
+        origin_pos: IVec3::from_array(json.origin),
+        blocks,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use valence::prelude::*;
+
+    use super::*;
+
+    #[test]
+    fn json_round_trips() {
+        let stairs = BlockState::OAK_STAIRS
+            .set(PropName::Facing, PropValue::East)
+            .set(PropName::Half, PropValue::Top);
+        let structure = Structure {
+            size: IVec3::new(2, 1, 2),
+            origin_pos: IVec3::new(1, 0, 0),
+            blocks: vec![
+                BlockState::STONE,
+                stairs,
+                BlockState::AIR,
+                BlockState::STONE,
+            ],
+        };
+        let loaded = from_json(&to_json(&structure).unwrap()).unwrap();
+        assert_eq!(loaded.size, structure.size);
+        assert_eq!(loaded.origin_pos, structure.origin_pos);
+        assert_eq!(loaded.blocks, structure.blocks);
+    }
+
+    #[test]
+    fn wrong_block_count_is_an_error() {
+        let json =
+            br#"{"size":[2,1,1],"origin":[0,0,0],"palette":["minecraft:stone"],"blocks":[0]}"#;
+        assert!(from_json(json).is_err());
+    }
+
+    #[test]
+    fn huge_sizes_are_an_error() {
+        let json = br#"{"size":[100000,100000,100000],"origin":[0,0,0],"palette":[],"blocks":[]}"#;
+        assert!(from_json(json).is_err());
+    }
+}
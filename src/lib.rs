@@ -0,0 +1,396 @@
+#![allow(clippy::type_complexity)]
+
+use bounds::{Bounds, Occupancy};
+use commands::{client_error, CommandPlugin};
+use config::{Config, Role};
+use diff::DiffPlugin;
+use grid::GridPlugin;
+use origin::OriginPlugin;
+use section::{Section, SectionPlugin};
+use valence::client::Username;
+use valence::command::scopes::CommandScopes;
+use valence::entity::UniqueId;
+use valence::interact_block::InteractBlockEvent;
+use valence::inventory::HeldItem;
+use valence::op_level::OpLevel;
+use valence::prelude::*;
+use valence::spawn::IsFlat;
+pub use valence_vstruc as structure;
+use watch::WatchPlugin;
+use workspace::{Dirty, Editing, Personal, Plots, Workspace};
+
+pub mod api;
+pub mod bounds;
+pub mod commands;
+pub mod config;
+pub mod diff;
+pub mod grid;
+pub mod json;
+pub mod origin;
+pub mod palette;
+pub mod save;
+pub mod section;
+pub mod snapshot;
+pub mod stats;
+pub mod text;
+pub mod void;
+pub mod vstruc;
+pub mod watch;
+pub mod workspace;
+//mod structure;
+/// Height of the default spawn point, where the origin of the first plot is
+pub const SPAWN_Y: i32 = 64;
+
+/// Adds the editor to an app, the app needs valence's `DefaultPlugins`.
+/// Players are given roles by the `Config` resource, which is the default config if it is not inserted.
+pub struct EditorPlugin {
+    /// spawn a layer for the editor, otherwise the layer marked with `EditorLayer` has to be spawned
+    pub spawn_layer: bool,
+    /// move every joining player into the editor,
+    /// otherwise players are moved into the editor when `JoinEditor` is inserted
+    pub join_on_connect: bool,
+    /// center of the first plot, the other plots are placed next to it along x
+    pub spawn: BlockPos,
+}
+
+impl Default for EditorPlugin {
+    fn default() -> Self {
+        Self {
+            spawn_layer: true,
+            join_on_connect: true,
+            spawn: BlockPos::new(0, SPAWN_Y, 0),
+        }
+    }
+}
+
+impl Plugin for EditorPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.world.contains_resource::<Config>() {
+            app.init_resource::<Config>();
+        }
+        app.insert_resource(Plots { spawn: self.spawn });
+        if self.spawn_layer {
+            app.add_systems(Startup, setup);
+        }
+        if self.join_on_connect {
+            app.add_systems(Update, join_on_connect);
+        }
+        app.add_plugins(SectionPlugin)
+            .add_plugins(OriginPlugin)
+            .add_plugins(GridPlugin)
+            .add_plugins(DiffPlugin)
+            .add_plugins(WatchPlugin)
+            .add_plugins(CommandPlugin)
+            .add_systems(
+                Update,
+                (
+                    init_clients,
+                    digging,
+                    place_blocks,
+                    workspace::update_labels,
+                ),
+            );
+    }
+}
+
+/// marker component for the layer workspaces are placed in
+#[derive(Component)]
+pub struct EditorLayer;
+
+/// marker component for clients that should be moved into the editor,
+/// which gives them their role and workspace
+#[derive(Component)]
+pub struct JoinEditor;
+
+fn setup(
+    mut commands: Commands,
+    server: Res<Server>,
+    dimensions: Res<DimensionTypeRegistry>,
+    biomes: Res<BiomeRegistry>,
+) {
+    let layer = LayerBundle::new(ident!("overworld"), &dimensions, &biomes, &server);
+
+    // the chunks are loaded when workspaces are spawned
+    commands.spawn((layer, EditorLayer));
+}
+
+fn join_on_connect(mut commands: Commands, clients: Query<Entity, Added<Client>>) {
+    for client in &clients {
+        commands.entity(client).insert(JoinEditor);
+    }
+}
+
+fn init_clients(
+    mut clients: Query<
+        (
+            Entity,
+            &mut Client,
+            &mut EntityLayerId,
+            &mut VisibleChunkLayer,
+            &mut VisibleEntityLayers,
+            &mut Position,
+            &mut GameMode,
+            &mut IsFlat,
+            &mut CommandScopes,
+            &mut OpLevel,
+            &mut Inventory,
+            &Username,
+            &UniqueId,
+        ),
+        (With<JoinEditor>, Without<Role>),
+    >,
+    mut layers: Query<(Entity, &mut ChunkLayer), With<EditorLayer>>,
+    workspaces: Query<(Entity, &Workspace, Has<Personal>)>,
+    config: Res<Config>,
+    plots: Res<Plots>,
+    mut commands: Commands,
+) {
+    // clients get their role when they are moved in, so they are retried until the layer exists
+    let Ok((layer, mut chunk_layer)) = layers.get_single_mut() else {
+        return;
+    };
+    // workspaces spawned this tick are not in the query yet
+    let mut spawned: Vec<(String, i32)> = Vec::new();
+
+    for (
+        entity,
+        mut client,
+        mut layer_id,
+        mut visible_chunk_layer,
+        mut visible_entity_layers,
+        mut pos,
+        mut game_mode,
+        mut is_flat,
+        mut permissions,
+        mut op_level,
+        mut inventory,
+        username,
+        uuid,
+    ) in &mut clients
+    {
+        layer_id.0 = layer;
+        visible_chunk_layer.0 = layer;
+        visible_entity_layers.0.insert(layer);
+
+        let role = config.role(&username.0, &uuid.0.to_string());
+        commands.entity(entity).insert(role);
+        *game_mode = role.game_mode();
+        is_flat.0 = true;
+        permissions.add(role.scope());
+        op_level.set(role.op_level());
+
+        if role == Role::Viewer {
+            // viewers can't edit, so they don't get a workspace
+            let plot = workspaces
+                .iter()
+                .map(|(_, workspace, _)| workspace.plot)
+                .min_by_key(|plot| plot.abs())
+                .unwrap_or(0);
+            pos.0 = plots.player_spawn(plot);
+            continue;
+        }
+
+        // every player gets their own workspace, which is kept when they leave
+        let (editing, plot) = match workspaces
+            .iter()
+            .find(|(_, workspace, personal)| *personal && workspace.owner == username.0)
+        {
+            Some((editing, workspace, _)) => (editing, workspace.plot),
+            None => {
+                let names: Vec<&str> = workspaces
+                    .iter()
+                    .map(|(_, workspace, _)| workspace.name.as_str())
+                    .chain(spawned.iter().map(|(name, _)| name.as_str()))
+                    .collect();
+                let name = workspace::unique_name(username.0.clone(), &names);
+                let used_plots: Vec<i32> = workspaces
+                    .iter()
+                    .map(|(_, workspace, _)| workspace.plot)
+                    .chain(spawned.iter().map(|(_, plot)| *plot))
+                    .collect();
+                let plot = workspace::free_plot(&used_plots);
+                let editing = match workspace::spawn_workspace(
+                    &mut commands,
+                    layer,
+                    &mut chunk_layer,
+                    name.clone(),
+                    username.0.clone(),
+                    &plots,
+                    plot,
+                    &workspace::empty_structure(),
+                    None,
+                    config.style(&name),
+                ) {
+                    Ok(editing) => editing,
+                    // only happens if the spawn point is outside of the world
+                    Err(e) => {
+                        client_error(&mut client, e);
+                        continue;
+                    }
+                };
+                commands.entity(editing).insert(Personal);
+                spawned.push((name, plot));
+                (editing, plot)
+            }
+        };
+        commands.entity(entity).insert(Editing(editing));
+        pos.0 = plots.player_spawn(plot);
+        // structure voids can't be picked from the creative inventory
+        inventory.set_slot(44, ItemStack::new(ItemKind::StructureVoid, 1, None));
+    }
+}
+
+fn digging(
+    clients: Query<(&GameMode, &Username, &Role, &VisibleChunkLayer)>,
+    mut layers: Query<(Entity, &mut ChunkLayer), With<EditorLayer>>,
+    mut events: EventReader<DiggingEvent>,
+    mut workspaces: Query<(
+        &Workspace,
+        &Bounds,
+        &mut Section,
+        &mut Occupancy,
+        &mut Dirty,
+    )>,
+    plots: Res<Plots>,
+) {
+    let Ok((layer_entity, mut layer)) = layers.get_single_mut() else {
+        return;
+    };
+
+    for event in events.read() {
+        let Ok((game_mode, username, role, visible_layer)) = clients.get(event.client) else {
+            continue;
+        };
+        // players in other layers of the server are not editing
+        if visible_layer.0 != layer_entity {
+            continue;
+        }
+
+        if (*game_mode == GameMode::Creative && event.state == DiggingState::Start)
+            || (*game_mode == GameMode::Survival && event.state == DiggingState::Stop)
+        {
+            let plot = plots.plot_of(event.position);
+            let Some((_, bounds, mut section, mut occupancy, mut dirty)) =
+                workspaces.iter_mut().find(|(workspace, ..)| {
+                    workspace.plot == plot && workspace.can_edit(&username.0, *role)
+                })
+            else {
+                continue;
+            };
+            occupancy.set_block(&mut layer, event.position, BlockState::AIR);
+            bounds.update(&mut section, &occupancy);
+            dirty.set_if_neq(Dirty(true));
+        }
+    }
+}
+
+fn place_blocks(
+    mut clients: Query<(
+        &Inventory,
+        &HeldItem,
+        &Look,
+        &Username,
+        &Role,
+        &VisibleChunkLayer,
+    )>,
+    mut workspaces: Query<(
+        &Workspace,
+        &Bounds,
+        &mut Section,
+        &mut Occupancy,
+        &mut Dirty,
+    )>,
+    mut layers: Query<(Entity, &mut ChunkLayer), With<EditorLayer>>,
+    mut events: EventReader<InteractBlockEvent>,
+    plots: Res<Plots>,
+) {
+    let Ok((layer_entity, mut layer)) = layers.get_single_mut() else {
+        return;
+    };
+
+    for event in events.read() {
+        let Ok((inventory, held, look, username, role, visible_layer)) =
+            clients.get_mut(event.client)
+        else {
+            continue;
+        };
+        if event.hand != Hand::Main || visible_layer.0 != layer_entity {
+            continue;
+        }
+
+        // get the held item
+        let slot_id = held.slot();
+        let stack = inventory.slot(slot_id);
+        if stack.is_empty() {
+            // no item in the slot
+            continue;
+        };
+
+        let Some(block_kind) = BlockKind::from_item_kind(stack.item) else {
+            // can't place this item as a block
+            continue;
+        };
+        let real_pos = event.position.get_in_direction(event.face);
+        let plot = plots.plot_of(real_pos);
+        let Some((_, bounds, mut section, mut occupancy, mut dirty)) =
+            workspaces.iter_mut().find(|(workspace, ..)| {
+                workspace.plot == plot && workspace.can_edit(&username.0, *role)
+            })
+        else {
+            // blocks outside of the players own workspaces would not be saved
+            continue;
+        };
+
+        let half = if event.face == Direction::Up {
+            PropValue::Bottom
+        } else if event.face == Direction::Down {
+            PropValue::Top
+        } else if event.cursor_pos.y < 0.5 {
+            PropValue::Bottom
+        } else {
+            PropValue::Top
+        };
+        let state = block_kind
+            .to_state()
+            .set(
+                PropName::Axis,
+                match event.face {
+                    Direction::Down | Direction::Up => PropValue::Y,
+                    Direction::North | Direction::South => PropValue::Z,
+                    Direction::West | Direction::East => PropValue::X,
+                },
+            )
+            .set(
+                PropName::Facing,
+                match look_to_dir(look) {
+                    Direction::South => PropValue::South,
+                    Direction::West => PropValue::West,
+                    Direction::East => PropValue::East,
+                    _ => PropValue::North,
+                },
+            )
+            .set(PropName::Half, half)
+            .set(PropName::Type, half);
+        //.set(PropName::Facing, event.client);
+
+        occupancy.set_block(&mut layer, real_pos, state);
+        bounds.update(&mut section, &occupancy);
+        dirty.set_if_neq(Dirty(true));
+    }
+}
+
+pub fn look_to_dir(look: &Look) -> Direction {
+    let dir = look.yaw % 360.0;
+    let dir = if dir < 0.0 { dir + 360.0 } else { dir };
+    let mut block_dir = Direction::North;
+    if (0.0..45.0).contains(&dir) || (315.0..360.0).contains(&dir) {
+        block_dir = Direction::South;
+    }
+    if (45.0..135.0).contains(&dir) {
+        block_dir = Direction::West;
+    }
+    if (225.0..315.0).contains(&dir) {
+        block_dir = Direction::East;
+    }
+    block_dir
+}
@@ -21,20 +21,22 @@ pub struct Origin {
     pub position: BlockPos,
 }
 
-pub fn spawn_origin(commands: &mut Commands, layer: Entity, position: BlockPos) {
+pub fn spawn_origin(commands: &mut Commands, layer: Entity, position: BlockPos) -> Entity {
     let mut entity_flags = Flags::default();
     entity_flags.set_glowing(true);
 
-    commands.spawn((
-        BlockDisplayEntityBundle {
-            block_display_block_state: block_display::BlockState(BlockState::RED_CONCRETE),
-            display_scale: Scale(Vec3::new(0.35, 0.35, 0.35)),
-            entity_flags,
-            layer: EntityLayerId(layer),
-            ..Default::default()
-        },
-        Origin { position },
-    ));
+    commands
+        .spawn((
+            BlockDisplayEntityBundle {
+                block_display_block_state: block_display::BlockState(BlockState::RED_CONCRETE),
+                display_scale: Scale(Vec3::new(0.35, 0.35, 0.35)),
+                entity_flags,
+                layer: EntityLayerId(layer),
+                ..Default::default()
+            },
+            Origin { position },
+        ))
+        .id()
 }
 
 fn origin_system(mut query: Query<(&mut Position, &Origin, &Scale)>) {
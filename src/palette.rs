@@ -0,0 +1,131 @@
+use std::collections::HashMap;
+
+use valence::{math::IVec3, prelude::*};
+
+/// The distinct block states of a structure, with every block as an index into them
+pub struct Palette {
+    pub states: Vec<BlockState>,
+    pub indices: Vec<u32>,
+}
+
+impl Palette {
+    /// States are in the order they first appear in
+    pub fn new(blocks: &[BlockState]) -> Self {
+        let mut states = Vec::new();
+        let mut lookup = HashMap::new();
+        let indices = blocks
+            .iter()
+            .map(|state| {
+                *lookup.entry(*state).or_insert_with(|| {
+                    states.push(*state);
+                    states.len() as u32 - 1
+                })
+            })
+            .collect();
+        Self { states, indices }
+    }
+
+    pub fn blocks(&self) -> Result<Vec<BlockState>, String> {
+        self.indices
+            .iter()
+            .map(|i| {
+                self.states
+                    .get(*i as usize)
+                    .copied()
+                    .ok_or_else(|| format!("block index {} is not in the palette", i))
+            })
+            .collect()
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.states.iter().map(|state| block_name(*state)).collect()
+    }
+
+    pub fn from_names(names: &[String], indices: Vec<u32>) -> Result<Self, String> {
+        let states = names
+            .iter()
+            .map(|name| parse_block(name))
+            .collect::<Result<_, _>>()?;
+        Ok(Self { states, indices })
+    }
+}
+
+/// The block as it is written in commands, like `minecraft:oak_stairs[facing=east,half=bottom]`
+pub fn block_name(state: BlockState) -> String {
+    let kind = state.to_kind();
+    let props: Vec<String> = kind
+        .props()
+        .iter()
+        .filter_map(|prop| {
+            state
+                .get(*prop)
+                .map(|value| format!("{}={}", prop.to_str(), value.to_str()))
+        })
+        .collect();
+    if props.is_empty() {
+        format!("minecraft:{}", kind.to_str())
+    } else {
+        format!("minecraft:{}[{}]", kind.to_str(), props.join(","))
+    }
+}
+
+/// Reads a block written by `block_name`, properties that are left out keep their default value
+pub fn parse_block(name: &str) -> Result<BlockState, String> {
+    let name = name.trim();
+    let (kind, props) = match name.split_once('[') {
+        Some((kind, props)) => (
+            kind,
+            props
+                .strip_suffix(']')
+                .ok_or_else(|| format!("missing ']' in '{}'", name))?,
+        ),
+        None => (name, ""),
+    };
+    let kind = kind.strip_prefix("minecraft:").unwrap_or(kind);
+    let mut state = BlockKind::from_str(kind)
+        .ok_or_else(|| format!("unknown block '{}'", kind))?
+        .to_state();
+    for prop in props.split(',').filter(|prop| !prop.is_empty()) {
+        let (prop, value) = prop
+            .split_once('=')
+            .ok_or_else(|| format!("missing '=' in '{}'", prop))?;
+        let prop_name = PropName::from_str(prop.trim())
+            .ok_or_else(|| format!("unknown property '{}'", prop))?;
+        let prop_value = PropValue::from_str(value.trim())
+            .ok_or_else(|| format!("unknown value '{}' of '{}'", value, prop))?;
+        let new_state = state.set(prop_name, prop_value);
+        if new_state.get(prop_name) != Some(prop_value) {
+            return Err(format!("'{}' can not have {}={}", kind, prop, value));
+        }
+        state = new_state;
+    }
+    Ok(state)
+}
+
+/// Index of the block at `pos` in the blocks of a structure,
+/// blocks are ordered by x, then y, then z, so z changes fastest
+pub fn block_index(size: IVec3, pos: IVec3) -> usize {
+    ((pos.x * size.y + pos.y) * size.z + pos.z) as usize
+}
+
+/// Most blocks a structure can have, a 256 block cube.
+/// Sizes come from the file, so larger ones are rejected before anything is allocated for them,
+/// and they are not saved either so every saved file can be read again.
+pub const MAX_VOLUME: usize = 1 << 24;
+
+/// Number of blocks in a structure of the size, `None` if it is negative or does not fit in a `usize`
+pub fn volume(size: IVec3) -> Option<usize> {
+    let [x, y, z] = size.to_array().map(|axis| usize::try_from(axis).ok());
+    x?.checked_mul(y?)?.checked_mul(z?)
+}
+
+/// Volume of a size read from a file, an error if it is empty or too large
+pub fn check_size(size: IVec3) -> Result<usize, String> {
+    match volume(size) {
+        Some(volume) if size.cmpge(IVec3::ONE).all() && volume <= MAX_VOLUME => Ok(volume),
+        _ => Err(format!(
+            "invalid size {}x{}x{}, structures can have at most {} blocks",
+            size.x, size.y, size.z, MAX_VOLUME
+        )),
+    }
+}
@@ -0,0 +1,157 @@
+use std::{
+    error::Error,
+    ffi::OsString,
+    fmt,
+    fs::{self, File},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::{json, palette, structure::Structure, text, vstruc};
+
+/// How many previous versions of a file are kept
+pub const BACKUPS: u32 = 3;
+
+#[derive(Debug)]
+pub enum SaveError {
+    /// the structure could not be turned into bytes
+    Serialization(String),
+    /// the file or one of its folders is not writable
+    PermissionDenied(io::Error),
+    Io(io::Error),
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SaveError::Serialization(e) => write!(f, "could not serialize the structure: {}", e),
+            SaveError::PermissionDenied(e) => write!(f, "permission denied: {}", e),
+            SaveError::Io(e) => write!(f, "could not write the file: {}", e),
+        }
+    }
+}
+
+impl Error for SaveError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SaveError::Serialization(_) => None,
+            SaveError::PermissionDenied(e) | SaveError::Io(e) => Some(e),
+        }
+    }
+}
+
+impl From<io::Error> for SaveError {
+    fn from(e: io::Error) -> Self {
+        if e.kind() == io::ErrorKind::PermissionDenied {
+            SaveError::PermissionDenied(e)
+        } else {
+            SaveError::Io(e)
+        }
+    }
+}
+
+/// How a structure is stored, chosen by the extension of the file
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Format {
+    /// `.vstruc`, a binary format with a palette of block names
+    Vstruc,
+    /// `.json`, a palette of block names and an index for every block
+    Json,
+    /// `.txt`, a line for every row of blocks, so changes show up in diffs of git and the like
+    Text,
+}
+
+impl Format {
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "vstruc" => Some(Format::Vstruc),
+            "json" => Some(Format::Json),
+            "txt" => Some(Format::Text),
+            _ => None,
+        }
+    }
+}
+
+/// Serializes the structure and writes it to the path, keeping backups of the previous versions
+pub fn save_structure(path: &Path, structure: &Structure) -> Result<(), SaveError> {
+    // larger structures could be written, but not read back
+    palette::check_size(structure.size).map_err(SaveError::Serialization)?;
+    let data = encode(structure, format_of(path)).map_err(SaveError::Serialization)?;
+    write_with_backups(path, &data)?;
+    Ok(())
+}
+
+/// Reads a structure saved with `save_structure`
+pub fn read_structure(path: &Path) -> Result<Structure, String> {
+    let data =
+        fs::read(path).map_err(|e| format!("error while trying to read structure data: {}", e))?;
+    decode(&data, format_of(path))
+}
+
+/// Files with unknown extensions are read and written as `.vstruc`
+fn format_of(path: &Path) -> Format {
+    Format::from_path(path).unwrap_or(Format::Vstruc)
+}
+
+fn encode(structure: &Structure, format: Format) -> Result<Vec<u8>, String> {
+    match format {
+        Format::Vstruc => Ok(vstruc::encode(structure)),
+        Format::Json => json::to_json(structure),
+        Format::Text => Ok(text::encode(structure)),
+    }
+}
+
+fn decode(data: &[u8], format: Format) -> Result<Structure, String> {
+    match format {
+        Format::Vstruc => vstruc::decode(data),
+        Format::Json => json::from_json(data),
+        Format::Text => text::decode(data),
+    }
+}
+
+/// Writes the data to the path, keeping backups of the previous versions.
+/// The data is written to a temporary file first, so the file is never left half written.
+pub fn write_with_backups(path: &Path, data: &[u8]) -> io::Result<()> {
+    fs::create_dir_all(path.parent().unwrap_or(Path::new("")))?;
+
+    let temp = with_suffix(path, ".tmp");
+    let result = write_synced(&temp, data).and_then(|_| {
+        if path.exists() {
+            rotate_backups(path)?;
+        }
+        fs::rename(&temp, path)
+    });
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    result
+}
+
+fn write_synced(path: &Path, data: &[u8]) -> io::Result<()> {
+    let mut file = File::create(path)?;
+    file.write_all(data)?;
+    file.sync_all()
+}
+
+/// Path of the backup, `1` being the newest
+pub fn backup_path(path: &Path, backup: u32) -> PathBuf {
+    with_suffix(path, &format!(".bak{}", backup))
+}
+
+fn rotate_backups(path: &Path) -> io::Result<()> {
+    for backup in (1..BACKUPS).rev() {
+        let from = backup_path(path, backup);
+        if from.exists() {
+            fs::rename(&from, backup_path(path, backup + 1))?;
+        }
+    }
+    // copied rather than moved, so there is a file at the path until it is replaced
+    fs::copy(path, backup_path(path, 1))?;
+    Ok(())
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = OsString::from(path.as_os_str());
+    name.push(suffix);
+    PathBuf::from(name)
+}
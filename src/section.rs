@@ -1,19 +1,53 @@
+use std::borrow::Cow;
+
 use valence::{
-    advancement::bevy_hierarchy::{BuildChildren, Children},
+    advancement::bevy_hierarchy::{BuildChildren, Children, Parent},
     entity::{
         block_display::{self, BlockDisplayEntityBundle},
-        display::Scale,
+        display::{Billboard, Scale},
         entity::Flags,
+        text_display::{self, TextDisplayEntityBundle},
+        UniqueId,
     },
     math::IVec3,
     prelude::*,
+    protocol::{
+        packets::play::{
+            team_s2c::{CollisionRule, Mode, NameTagVisibility, TeamColor, TeamFlags},
+            TeamS2c,
+        },
+        WritePacket,
+    },
 };
 
+/// Glow colors by their chat color name
+const COLORS: [(&str, TeamColor); 16] = [
+    ("black", TeamColor::Black),
+    ("dark_blue", TeamColor::DarkBlue),
+    ("dark_green", TeamColor::DarkGreen),
+    ("dark_aqua", TeamColor::DarkCyan),
+    ("dark_red", TeamColor::DarkRed),
+    ("dark_purple", TeamColor::Purple),
+    ("gold", TeamColor::Gold),
+    ("gray", TeamColor::Gray),
+    ("dark_gray", TeamColor::DarkGray),
+    ("blue", TeamColor::Blue),
+    ("green", TeamColor::BrightGreen),
+    ("aqua", TeamColor::Cyan),
+    ("red", TeamColor::Red),
+    ("light_purple", TeamColor::Pink),
+    ("yellow", TeamColor::Yellow),
+    ("white", TeamColor::White),
+];
+
 pub struct SectionPlugin;
 
 impl Plugin for SectionPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update, update_sections);
+        app.add_systems(
+            Update,
+            (update_sections, update_glow_teams, despawn_section_parts),
+        );
     }
 }
 
@@ -28,6 +62,12 @@ pub struct Section {
     pub corner_block: BlockState,
     pub glowing_walls: bool,
     pub glowing_corners: bool,
+    /// glow color of the walls, white if `None`
+    pub wall_color: Option<TeamColor>,
+    /// glow color of the corners, white if `None`
+    pub corner_color: Option<TeamColor>,
+    /// shown above the section along with its size
+    pub label: Option<String>,
 }
 
 impl Default for Section {
@@ -42,6 +82,9 @@ impl Default for Section {
             corner_block: BlockState::RED_CONCRETE,
             glowing_walls: true,
             glowing_corners: true,
+            wall_color: None,
+            corner_color: None,
+            label: None,
         }
     }
 }
@@ -52,6 +95,14 @@ struct SectionWall(u8);
 #[derive(Component)]
 struct SectionCorner(u8);
 
+#[derive(Component)]
+struct SectionLabel;
+
+/// The glow color team a wall or corner was last added to
+#[derive(Component, Default)]
+struct GlowTeam(Option<TeamColor>);
+
+/// Only components that differ are written, so unchanged walls and corners send no packets
 fn update_sections(
     mut commands: Commands,
     mut sections: Query<
@@ -77,16 +128,43 @@ fn update_sections(
         ),
         Without<SectionWall>,
     >,
+    mut labels: Query<
+        (&mut text_display::Text, &mut Position, &mut EntityLayerId),
+        (
+            With<SectionLabel>,
+            Without<SectionWall>,
+            Without<SectionCorner>,
+        ),
+    >,
 ) {
     for (entity, section, children) in &mut sections {
-        if children.is_none() {
+        // the entity may have other children, or its parts may have been removed
+        let has_parts =
+            children.is_some_and(|children| children.iter().any(|child| walls.contains(*child)));
+        if !has_parts {
             commands.entity(entity).with_children(|builder| {
                 for i in 0..12 {
-                    builder.spawn((BlockDisplayEntityBundle::default(), SectionWall(i)));
+                    builder.spawn((
+                        BlockDisplayEntityBundle::default(),
+                        SectionWall(i),
+                        GlowTeam::default(),
+                    ));
                 }
                 for i in 0..8 {
-                    builder.spawn((BlockDisplayEntityBundle::default(), SectionCorner(i)));
+                    builder.spawn((
+                        BlockDisplayEntityBundle::default(),
+                        SectionCorner(i),
+                        GlowTeam::default(),
+                    ));
                 }
+                builder.spawn((
+                    TextDisplayEntityBundle {
+                        // always faces the player
+                        display_billboard: Billboard(3),
+                        ..Default::default()
+                    },
+                    SectionLabel,
+                ));
             });
             continue;
         }
@@ -104,10 +182,10 @@ fn update_sections(
             {
                 let mut entity_flags = Flags::default();
                 entity_flags.set_glowing(section.glowing_walls);
-                *wall_state = block_display::BlockState(section.wall_block);
-                *wall_flags = entity_flags;
-                *wall_layer = section.layer;
-                *wall_scale = match wall.0 / 4 {
+                wall_state.set_if_neq(block_display::BlockState(section.wall_block));
+                wall_flags.set_if_neq(entity_flags);
+                wall_layer.set_if_neq(section.layer);
+                let scale = match wall.0 / 4 {
                     0 => Scale(Vec3::new(
                         section.wall_width,
                         section.size.y as f32,
@@ -127,7 +205,8 @@ fn update_sections(
                 };
                 let a = wall.0 % 4 / 2;
                 let b = wall.0 % 2;
-                *wall_pos = Position(
+                wall_scale.set_if_neq(scale);
+                wall_pos.set_if_neq(Position(
                     match wall.0 / 4 {
                         0 => center_wall(
                             DVec3::new(
@@ -135,7 +214,7 @@ fn update_sections(
                                 (section.size.y) as f64 / 2.0,
                                 (b as i32 * section.size.z) as f64,
                             ),
-                            wall_scale.0.into(),
+                            scale.0.into(),
                         ),
                         1 => center_wall(
                             DVec3::new(
@@ -143,7 +222,7 @@ fn update_sections(
                                 (a as i32 * section.size.y) as f64,
                                 (b as i32 * section.size.z) as f64,
                             ),
-                            wall_scale.0.into(),
+                            scale.0.into(),
                         ),
                         2 => center_wall(
                             DVec3::new(
@@ -151,7 +230,7 @@ fn update_sections(
                                 (a as i32 * section.size.y) as f64,
                                 (section.size.z) as f64 / 2.0,
                             ),
-                            wall_scale.0.into(),
+                            scale.0.into(),
                         ),
                         _ => DVec3::ZERO,
                     } + DVec3::new(
@@ -159,7 +238,7 @@ fn update_sections(
                         section.position.y as f64,
                         section.position.z as f64,
                     ),
-                );
+                ));
             }
 
             // corners
@@ -174,11 +253,11 @@ fn update_sections(
             {
                 let mut entity_flags = Flags::default();
                 entity_flags.set_glowing(section.glowing_corners);
-                *corner_state = block_display::BlockState(section.corner_block);
-                *corner_flags = entity_flags;
-                *corner_layer = section.layer;
-                *corner_scale = Scale(Vec3::splat(section.corner_size));
-                *corner_pos = center_corner(
+                corner_state.set_if_neq(block_display::BlockState(section.corner_block));
+                corner_flags.set_if_neq(entity_flags);
+                corner_layer.set_if_neq(section.layer);
+                corner_scale.set_if_neq(Scale(Vec3::splat(section.corner_size)));
+                corner_pos.set_if_neq(center_corner(
                     section.position
                         + IVec3::new(
                             (corner.0 as i32 % 2) * section.size.x,
@@ -186,7 +265,25 @@ fn update_sections(
                             ((corner.0 / 2) as i32 % 2) * section.size.z,
                         ),
                     section.corner_size,
-                );
+                ));
+            }
+
+            // label
+            if let Ok((mut text, mut label_pos, mut label_layer)) = labels.get_mut(*child) {
+                let size = format!("{}x{}x{}", section.size.x, section.size.y, section.size.z);
+                text.set_if_neq(text_display::Text(
+                    match &section.label {
+                        Some(label) => format!("{}\n{}", label, size),
+                        None => size,
+                    }
+                    .into(),
+                ));
+                label_layer.set_if_neq(section.layer);
+                label_pos.set_if_neq(Position(DVec3::new(
+                    section.position.x as f64 + section.size.x as f64 / 2.0,
+                    (section.position.y + section.size.y) as f64 + 0.5,
+                    section.position.z as f64 + section.size.z as f64 / 2.0,
+                )));
             }
         }
     }
@@ -207,3 +304,128 @@ fn center_corner(pos: BlockPos, size: f32) -> Position {
 fn center_wall(pos: DVec3, size: DVec3) -> DVec3 {
     pos - size / 2.0
 }
+
+/// Despawns the walls, corners and label of sections that were removed or despawned
+fn despawn_section_parts(
+    mut commands: Commands,
+    mut removed: RemovedComponents<Section>,
+    parts: Query<
+        (Entity, &Parent),
+        Or<(With<SectionWall>, With<SectionCorner>, With<SectionLabel>)>,
+    >,
+) {
+    for section in removed.read() {
+        let section_parts: Vec<Entity> = parts
+            .iter()
+            .filter(|(_, parent)| parent.get() == section)
+            .map(|(part, _)| part)
+            .collect();
+        // the entity is still around if only the component was removed
+        if let Some(mut entity) = commands.get_entity(section) {
+            entity.remove_children(&section_parts);
+        }
+        for part in section_parts {
+            commands.entity(part).insert(Despawned);
+        }
+    }
+}
+
+/// Glowing entities glow in the color of their team,
+/// so walls and corners are added to a team for every color
+fn update_glow_teams(
+    // `Added<Client>` would conflict with `&mut Client`, so new clients are found with `is_added`
+    mut clients: Query<&mut Client>,
+    sections: Query<(&Section, &Children)>,
+    mut parts: Query<(&UniqueId, &mut GlowTeam, Has<SectionWall>)>,
+) {
+    for (section, children) in &sections {
+        for child in children.iter() {
+            let Ok((uuid, mut team, is_wall)) = parts.get_mut(*child) else {
+                continue;
+            };
+            let color = if is_wall {
+                section.wall_color
+            } else {
+                section.corner_color
+            };
+            if team.0 == color {
+                continue;
+            }
+            let uuid = uuid.0.to_string();
+            // joining a team leaves the previous one
+            let (name, mode) = match (color, team.0) {
+                (Some(color), _) => (
+                    team_name(color),
+                    Mode::AddEntities {
+                        entities: vec![&uuid],
+                    },
+                ),
+                (None, Some(old)) => (
+                    team_name(old),
+                    Mode::RemoveEntities {
+                        entities: vec![&uuid],
+                    },
+                ),
+                (None, None) => continue,
+            };
+            let packet = TeamS2c {
+                team_name: &name,
+                mode,
+            };
+            // new clients get every team with its members below
+            for mut client in &mut clients {
+                if !client.is_added() {
+                    client.write_packet(&packet);
+                }
+            }
+            team.0 = color;
+        }
+    }
+
+    for mut client in &mut clients {
+        if !client.is_added() {
+            continue;
+        }
+        for (_, color) in COLORS {
+            let members: Vec<String> = parts
+                .iter()
+                .filter(|(_, team, _)| team.0 == Some(color))
+                .map(|(uuid, _, _)| uuid.0.to_string())
+                .collect();
+            client.write_packet(&TeamS2c {
+                team_name: &team_name(color),
+                mode: Mode::CreateTeam {
+                    team_display_name: Cow::Owned(Text::default()),
+                    friendly_flags: TeamFlags::new(),
+                    name_tag_visibility: NameTagVisibility::Always,
+                    collision_rule: CollisionRule::Always,
+                    team_color: color,
+                    team_prefix: Cow::Owned(Text::default()),
+                    team_suffix: Cow::Owned(Text::default()),
+                    entities: members.iter().map(String::as_str).collect(),
+                },
+            });
+        }
+    }
+}
+
+fn team_name(color: TeamColor) -> String {
+    format!("valence_editor.{}", color_name(color))
+}
+
+/// The chat color name of the glow color
+pub fn color_name(color: TeamColor) -> &'static str {
+    COLORS
+        .iter()
+        .find(|(_, c)| *c == color)
+        .map(|(name, _)| *name)
+        .unwrap_or("white")
+}
+
+/// The glow color with the chat color name
+pub fn parse_color(name: &str) -> Option<TeamColor> {
+    COLORS
+        .iter()
+        .find(|(n, _)| n.eq_ignore_ascii_case(name))
+        .map(|(_, color)| *color)
+}
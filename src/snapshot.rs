@@ -0,0 +1,86 @@
+//! Named versions of a structure, saved in a hidden folder next to its file,
+//! like `.house.vstruc.snapshots/roof_idea.vstruc` for `house.vstruc`.
+
+use std::{
+    ffi::OsString,
+    fs, io,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+const EXTENSION: &str = "vstruc";
+
+/// The folder with the snapshots of the structure saved at `path`
+pub fn folder(path: &Path) -> PathBuf {
+    let mut name = OsString::from(".");
+    name.push(path.file_name().unwrap_or_default());
+    name.push(".snapshots");
+    path.with_file_name(name)
+}
+
+/// Path of the snapshot with the name, names can not contain path separators
+pub fn path(path: &Path, name: &str) -> Result<PathBuf, String> {
+    let valid = !name.is_empty()
+        && !name.starts_with('.')
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '-' | '_' | ' '));
+    if !valid {
+        return Err(format!(
+            "'{}' is not a valid snapshot name, use letters, numbers, '-', '_' and spaces",
+            name
+        ));
+    }
+    Ok(folder(path).join(format!("{}.{}", name, EXTENSION)))
+}
+
+/// Names of the snapshots of the structure saved at `path`, oldest first
+pub fn list(path: &Path) -> io::Result<Vec<String>> {
+    let entries = match fs::read_dir(folder(path)) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    let mut snapshots = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        if path.extension().and_then(|e| e.to_str()) != Some(EXTENSION) {
+            continue;
+        }
+        let Some(name) = path.file_stem().and_then(|n| n.to_str()) else {
+            continue;
+        };
+        let modified = fs::metadata(&path)
+            .and_then(|m| m.modified())
+            .unwrap_or(SystemTime::UNIX_EPOCH);
+        snapshots.push((modified, name.to_string()));
+    }
+    snapshots.sort();
+    Ok(snapshots.into_iter().map(|(_, name)| name).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snapshots_are_next_to_the_file() {
+        let file = Path::new("structures/house.vstruc");
+        assert_eq!(
+            folder(file),
+            Path::new("structures/.house.vstruc.snapshots")
+        );
+        assert_eq!(
+            path(file, "roof idea").unwrap(),
+            Path::new("structures/.house.vstruc.snapshots/roof idea.vstruc")
+        );
+    }
+
+    #[test]
+    fn names_can_not_leave_the_folder() {
+        let file = Path::new("house.vstruc");
+        for name in ["", "..", "../house", "a/b", "a\\b", ".hidden"] {
+            assert!(path(file, name).is_err(), "{}", name);
+        }
+    }
+}
@@ -0,0 +1,155 @@
+use std::collections::HashMap;
+
+use valence::{math::IVec3, prelude::*};
+
+use crate::void::STRUCTURE_VOID;
+
+/// Number of stacks a shulker box holds
+const SHULKER_SLOTS: u64 = 27;
+
+/// Counts every block kind in the area, except air and structure voids.
+/// Sorted with the most common kind first.
+pub fn block_counts(layer: &ChunkLayer, position: BlockPos, size: IVec3) -> Vec<(BlockKind, u64)> {
+    let mut counts = HashMap::new();
+    for_each_state(layer, position, size, |state| {
+        *counts.entry(state.to_kind()).or_insert(0) += 1;
+    });
+    sorted(counts, |kind| kind.to_str())
+}
+
+/// The items needed to build the blocks in the area
+pub fn materials(layer: &ChunkLayer, position: BlockPos, size: IVec3) -> Vec<Material> {
+    let mut counts = HashMap::new();
+    for_each_state(layer, position, size, |state| {
+        if let Some((item, count)) = item_cost(state) {
+            *counts.entry(item).or_insert(0) += count;
+        }
+    });
+    sorted(counts, |item| item.to_str())
+        .into_iter()
+        .map(|(item, count)| Material { item, count })
+        .collect()
+}
+
+pub struct Material {
+    pub item: ItemKind,
+    pub count: u64,
+}
+
+impl Material {
+    pub fn stacks(&self) -> u64 {
+        let max_stack = (self.item.max_stack() as u64).max(1);
+        self.count.div_ceil(max_stack)
+    }
+
+    /// Shulker boxes filled by this item alone
+    pub fn shulker_boxes(&self) -> u64 {
+        self.stacks().div_ceil(SHULKER_SLOTS)
+    }
+}
+
+/// Shulker boxes needed for all materials, different items can share a box
+pub fn shulker_boxes(materials: &[Material]) -> u64 {
+    let stacks: u64 = materials.iter().map(Material::stacks).sum();
+    stacks.div_ceil(SHULKER_SLOTS)
+}
+
+/// The materials as csv, with a header row and a total row
+pub fn materials_csv(materials: &[Material]) -> String {
+    let mut csv = String::from("item,count,stacks,shulker_boxes\n");
+    for material in materials {
+        csv += &format!(
+            "minecraft:{},{},{},{}\n",
+            material.item.to_str(),
+            material.count,
+            material.stacks(),
+            material.shulker_boxes()
+        );
+    }
+    csv += &format!(
+        "total,{},{},{}\n",
+        materials.iter().map(|m| m.count).sum::<u64>(),
+        materials.iter().map(Material::stacks).sum::<u64>(),
+        shulker_boxes(materials)
+    );
+    csv
+}
+
+/// The item and amount needed to place the block,
+/// blocks that are part of another block like the top of a door cost nothing
+fn item_cost(state: BlockState) -> Option<(ItemKind, u64)> {
+    let item = state.to_kind().to_item_kind();
+    if item == ItemKind::Air {
+        return None;
+    }
+    if state.get(PropName::Half) == Some(PropValue::Upper)
+        || state.get(PropName::Part) == Some(PropValue::Head)
+    {
+        return None;
+    }
+    if state.get(PropName::Type) == Some(PropValue::Double) {
+        return Some((item, 2));
+    }
+    Some((item, 1))
+}
+
+fn for_each_state(
+    layer: &ChunkLayer,
+    position: BlockPos,
+    size: IVec3,
+    mut f: impl FnMut(BlockState),
+) {
+    for x in 0..size.x {
+        for y in 0..size.y {
+            for z in 0..size.z {
+                let Some(block) = layer.block(position + IVec3::new(x, y, z)) else {
+                    continue;
+                };
+                if block.state != BlockState::AIR && block.state != STRUCTURE_VOID {
+                    f(block.state);
+                }
+            }
+        }
+    }
+}
+
+/// Most common first, ties are sorted by name
+fn sorted<T>(counts: HashMap<T, u64>, name: impl Fn(&T) -> &'static str) -> Vec<(T, u64)> {
+    let mut counts: Vec<_> = counts.into_iter().collect();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| name(&a.0).cmp(name(&b.0))));
+    counts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn different_items_share_shulker_boxes() {
+        let materials = [
+            Material {
+                item: ItemKind::Stone,
+                count: 10 * 64,
+            },
+            Material {
+                item: ItemKind::Dirt,
+                count: 10 * 64 + 1,
+            },
+        ];
+        assert_eq!(materials[0].shulker_boxes(), 1);
+        assert_eq!(materials[1].shulker_boxes(), 1);
+        // 21 stacks fit in one box
+        assert_eq!(shulker_boxes(&materials), 1);
+        assert!(materials_csv(&materials).ends_with("total,1281,21,1\n"));
+    }
+
+    #[test]
+    fn full_shulker_boxes_are_not_rounded_up() {
+        let materials = [Material {
+            item: ItemKind::Stone,
+            count: SHULKER_SLOTS * 64,
+        }];
+        assert_eq!(shulker_boxes(&materials), 1);
+        assert_eq!(shulker_boxes(&[]), 0);
+    }
+}
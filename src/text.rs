@@ -0,0 +1,270 @@
+//! A text format for structures, made to be kept in git.
+//!
+//! Every layer is a block of lines seen from above, with a line for every z and
+//! a key for every x, so changing a block changes one line of the file.
+//!
+//! ```text
+//! vstruc text 1
+//! size 3 2 2
+//! origin 1 0 0
+//!
+//! . minecraft:air
+//! a minecraft:oak_stairs[facing=east,half=bottom]
+//! b minecraft:stone
+//!
+//! layer 0
+//! bba
+//! ..a
+//!
+//! layer 1
+//! ...
+//! .b.
+//! ```
+//!
+//! Air is always the first key and the other blocks are sorted by name,
+//! so the same structure is always written the same way.
+//! Keys are longer than one character when there are more blocks than characters.
+
+use std::fmt::Write;
+
+use valence::{math::IVec3, prelude::*};
+
+use crate::{
+    palette::{self, Palette},
+    structure::Structure,
+};
+
+const HEADER: &str = "vstruc text 1";
+/// Characters used for the keys of the palette, the first one is air
+const KEYS: &[u8] =
+    b".abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789!$%&*+-/:;<=>?@^_~";
+
+pub fn encode(structure: &Structure) -> Vec<u8> {
+    let palette = Palette::new(&structure.blocks);
+    let names = palette.names();
+    // air first, then by name
+    let mut order: Vec<usize> = (0..names.len()).collect();
+    order.sort_by_key(|i| (palette.states[*i] != BlockState::AIR, &names[*i]));
+    let mut keys = vec![String::new(); names.len()];
+    let width = key_width(names.len());
+    for (key, i) in order.iter().enumerate() {
+        keys[*i] = key_name(key, width);
+    }
+
+    let size = structure.size;
+    let origin = structure.origin_pos;
+    let mut text = String::new();
+    // writing to a string never fails
+    writeln!(text, "{}", HEADER).unwrap();
+    writeln!(text, "size {} {} {}", size.x, size.y, size.z).unwrap();
+    writeln!(text, "origin {} {} {}", origin.x, origin.y, origin.z).unwrap();
+    text.push('\n');
+    for i in &order {
+        writeln!(text, "{} {}", keys[*i], names[*i]).unwrap();
+    }
+    for y in 0..size.y {
+        write!(text, "\nlayer {}\n", y).unwrap();
+        for z in 0..size.z {
+            for x in 0..size.x {
+                let index = palette.indices[palette::block_index(size, IVec3::new(x, y, z))];
+                text += &keys[index as usize];
+            }
+            text.push('\n');
+        }
+    }
+    text.into_bytes()
+}
+
+pub fn decode(data: &[u8]) -> Result<Structure, String> {
+    let text = std::str::from_utf8(data).map_err(|_| "the file is not valid utf-8".to_string())?;
+    let mut lines = text
+        .lines()
+        .map(str::trim_end)
+        .enumerate()
+        .filter(|(_, line)| !line.is_empty())
+        .map(|(number, line)| (number + 1, line));
+    let mut next = |expected: &str| {
+        lines
+            .next()
+            .ok_or_else(|| format!("the file ends before {}", expected))
+    };
+
+    let (number, header) = next("the header")?;
+    if header != HEADER {
+        return Err(format!(
+            "line {}: expected '{}', the file is not a text structure",
+            number, HEADER
+        ));
+    }
+    let size = parse_vector(next("the size")?, "size")?;
+    let origin_pos = parse_vector(next("the origin")?, "origin")?;
+    let volume = palette::check_size(size)?;
+
+    // the palette goes until the first layer
+    let mut names = Vec::new();
+    let mut keys = Vec::new();
+    let mut line = next("the first layer")?;
+    while !line.1.starts_with("layer ") {
+        let Some((key, name)) = line.1.split_once(' ') else {
+            return Err(format!("line {}: expected a key and a block", line.0));
+        };
+        keys.push(key.to_string());
+        names.push(name.to_string());
+        line = next("the first layer")?;
+    }
+    let width = keys.first().map_or(1, String::len);
+    if keys.iter().any(|key| key.len() != width) {
+        return Err("the keys of the palette are not all the same length".to_string());
+    }
+
+    let mut indices = vec![0; volume];
+    for y in 0..size.y {
+        if y > 0 {
+            line = next(&format!("layer {}", y))?;
+        }
+        if line.1 != format!("layer {}", y) {
+            return Err(format!("line {}: expected 'layer {}'", line.0, y));
+        }
+        for z in 0..size.z {
+            let (number, row) = next(&format!("row {} of layer {}", z, y))?;
+            if row.len() != size.x as usize * width {
+                return Err(format!(
+                    "line {}: expected {} blocks, got '{}'",
+                    number, size.x, row
+                ));
+            }
+            for x in 0..size.x {
+                let start = x as usize * width;
+                let key = row.get(start..start + width).unwrap_or_default();
+                let Some(index) = keys.iter().position(|k| k == key) else {
+                    return Err(format!("line {}: '{}' is not in the palette", number, key));
+                };
+                indices[palette::block_index(size, IVec3::new(x, y, z))] = index as u32;
+            }
+        }
+    }
+    if let Some((number, _)) = lines.next() {
+        return Err(format!(
+            "line {}: the file has more layers than its size",
+            number
+        ));
+    }
+
+    Ok(Structure {
+        size,
+        origin_pos,
+        blocks: Palette::from_names(&names, indices)?.blocks()?,
+    })
+}
+
+/// How many characters a key needs so every block has its own key
+fn key_width(blocks: usize) -> usize {
+    let mut width = 1;
+    let mut keys = KEYS.len();
+    while keys < blocks {
+        width += 1;
+        keys *= KEYS.len();
+    }
+    width
+}
+
+fn key_name(mut index: usize, width: usize) -> String {
+    let mut key = vec![0; width];
+    for byte in key.iter_mut().rev() {
+        *byte = KEYS[index % KEYS.len()];
+        index /= KEYS.len();
+    }
+    // the keys are ascii
+    String::from_utf8(key).unwrap()
+}
+
+/// Reads a line like `size 1 2 3`
+fn parse_vector((number, line): (usize, &str), name: &str) -> Result<IVec3, String> {
+    let error = || format!("line {}: expected '{} <x> <y> <z>'", number, name);
+    let mut parts = line.split(' ');
+    if parts.next() != Some(name) {
+        return Err(error());
+    }
+    let values: Vec<i32> = parts
+        .map(|part| part.parse().map_err(|_| error()))
+        .collect::<Result<_, _>>()?;
+    match values[..] {
+        [x, y, z] => Ok(IVec3::new(x, y, z)),
+        _ => Err(error()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn structure() -> Structure {
+        let size = IVec3::new(3, 2, 2);
+        let mut blocks = vec![BlockState::AIR; palette::volume(size).unwrap()];
+        blocks[palette::block_index(size, IVec3::new(0, 0, 0))] = BlockState::STONE;
+        blocks[palette::block_index(size, IVec3::new(2, 0, 1))] = BlockState::DIRT;
+        blocks[palette::block_index(size, IVec3::new(1, 1, 1))] = BlockState::STONE;
+        Structure {
+            size,
+            origin_pos: IVec3::new(1, 0, 0),
+            blocks,
+        }
+    }
+
+    #[test]
+    fn round_trips() {
+        let structure = structure();
+        let loaded = decode(&encode(&structure)).unwrap();
+        assert_eq!(loaded.size, structure.size);
+        assert_eq!(loaded.origin_pos, structure.origin_pos);
+        assert_eq!(loaded.blocks, structure.blocks);
+    }
+
+    #[test]
+    fn layers_are_seen_from_above() {
+        let text = String::from_utf8(encode(&structure())).unwrap();
+        assert_eq!(
+            text,
+            "vstruc text 1\n\
+             size 3 2 2\n\
+             origin 1 0 0\n\
+             \n\
+             . minecraft:air\n\
+             a minecraft:dirt\n\
+             b minecraft:stone\n\
+             \n\
+             layer 0\n\
+             b..\n\
+             ..a\n\
+             \n\
+             layer 1\n\
+             ...\n\
+             .b.\n"
+        );
+    }
+
+    #[test]
+    fn long_palettes_use_longer_keys() {
+        assert_eq!(key_width(KEYS.len()), 1);
+        assert_eq!(key_width(KEYS.len() + 1), 2);
+        assert_eq!(key_name(0, 2), "..");
+        assert_eq!(key_name(KEYS.len() + 1, 2), "aa");
+    }
+
+    #[test]
+    fn invalid_files_are_an_error() {
+        let text = String::from_utf8(encode(&structure())).unwrap();
+        for invalid in [
+            text.replace("vstruc text 1", "vstruc text 2"),
+            text.replace("size 3 2 2", "size 100000 100000 100000"),
+            text.replace("size 3 2 2", "size 2147483647 2147483647 2147483647"),
+            text.replace("layer 1", "layer 2"),
+            text.replace(".b.", ".c."),
+            text.replace("..a", "..aa"),
+            text.replace("\n.b.\n", "\n"),
+            text.clone() + "...\n",
+        ] {
+            assert!(decode(invalid.as_bytes()).is_err(), "{}", invalid);
+        }
+    }
+}
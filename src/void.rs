@@ -0,0 +1,50 @@
+use valence::{math::IVec3, prelude::*};
+
+use crate::structure::Structure;
+
+/// Placed in the editor where the structure should leave the existing blocks as they are,
+/// as opposed to air which clears them.
+pub const STRUCTURE_VOID: BlockState = BlockState::STRUCTURE_VOID;
+
+/// Renders the structure like [`Structure::render_to_layer`],
+/// but keeps the existing blocks where the structure has structure voids.
+pub fn paste(structure: &Structure, layer: &mut ChunkLayer, origin: BlockPos) {
+    let start = origin - structure.origin_pos;
+    let mut previous = Vec::new();
+    for_each_pos(start, structure.size, |pos| {
+        previous.push(layer.block(pos).map(|block| Block {
+            state: block.state,
+            nbt: block.nbt.cloned(),
+        }))
+    });
+
+    structure.render_to_layer(layer, origin);
+
+    let mut previous = previous.into_iter();
+    for_each_pos(start, structure.size, |pos| {
+        let old = previous.next().flatten();
+        let is_void = layer
+            .block(pos)
+            .map(|block| block.state == STRUCTURE_VOID)
+            .unwrap_or(false);
+        if is_void {
+            layer.set_block(
+                pos,
+                old.unwrap_or(Block {
+                    state: BlockState::AIR,
+                    nbt: None,
+                }),
+            );
+        }
+    });
+}
+
+fn for_each_pos(start: BlockPos, size: IVec3, mut f: impl FnMut(BlockPos)) {
+    for x in 0..size.x {
+        for y in 0..size.y {
+            for z in 0..size.z {
+                f(start + IVec3::new(x, y, z));
+            }
+        }
+    }
+}
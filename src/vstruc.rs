@@ -0,0 +1,261 @@
+//! Version 2 of the `.vstruc` format.
+//!
+//! Blocks are stored as names like `minecraft:oak_stairs[facing=east,half=bottom]`,
+//! so files keep working when block state ids change between versions of valence.
+//! Every block is an index into the palette of names, stored as runs of the same index.
+//!
+//! - `VSTRUC` followed by the version byte
+//! - size and origin, 6 little endian `i32`s
+//! - number of names, then every name as a length and utf-8 bytes
+//! - number of runs, then every run as an index and a length
+//!
+//! Numbers after the origin are LEB128 varints.
+//! Files without the header are read as the bincode format of valence_vstruc.
+
+use valence::math::IVec3;
+
+use crate::{
+    palette::{self, Palette},
+    structure::Structure,
+};
+
+const MAGIC: &[u8] = b"VSTRUC";
+const VERSION: u8 = 2;
+
+pub fn encode(structure: &Structure) -> Vec<u8> {
+    let palette = Palette::new(&structure.blocks);
+    let mut data = MAGIC.to_vec();
+    data.push(VERSION);
+    for value in structure
+        .size
+        .to_array()
+        .into_iter()
+        .chain(structure.origin_pos.to_array())
+    {
+        data.extend_from_slice(&value.to_le_bytes());
+    }
+
+    let names = palette.names();
+    write_varint(&mut data, names.len() as u64);
+    for name in names {
+        write_varint(&mut data, name.len() as u64);
+        data.extend_from_slice(name.as_bytes());
+    }
+
+    let runs = runs(&palette.indices);
+    write_varint(&mut data, runs.len() as u64);
+    for (index, length) in runs {
+        write_varint(&mut data, index as u64);
+        write_varint(&mut data, length as u64);
+    }
+    data
+}
+
+/// Reads both this format and the old bincode format
+pub fn decode(data: &[u8]) -> Result<Structure, String> {
+    let Some(rest) = data.strip_prefix(MAGIC) else {
+        return decode_bincode(data);
+    };
+    let mut reader = Reader { data: rest };
+    let version = reader.bytes(1)?[0];
+    if version != VERSION {
+        return Err(format!(
+            "the file is version {} of the format, but only version {} is supported",
+            version, VERSION
+        ));
+    }
+    let mut vectors = [0; 6];
+    for value in &mut vectors {
+        *value = i32::from_le_bytes(reader.bytes(4)?.try_into().unwrap());
+    }
+    let size = IVec3::new(vectors[0], vectors[1], vectors[2]);
+    let origin_pos = IVec3::new(vectors[3], vectors[4], vectors[5]);
+    let volume = palette::check_size(size)?;
+
+    let mut names = Vec::new();
+    for _ in 0..reader.varint()? {
+        let length = reader.varint()? as usize;
+        let name = std::str::from_utf8(reader.bytes(length)?)
+            .map_err(|_| "a block name is not valid utf-8".to_string())?;
+        names.push(name.to_string());
+    }
+
+    // not allocated up front, the runs have to actually contain the blocks
+    let mut indices = Vec::new();
+    for _ in 0..reader.varint()? {
+        let index = reader.varint()? as u32;
+        let length = reader.varint()? as usize;
+        if length > volume - indices.len() {
+            return Err("the file has more blocks than its size".to_string());
+        }
+        indices.extend(std::iter::repeat(index).take(length));
+    }
+    if indices.len() != volume {
+        return Err(format!(
+            "expected {} blocks for the size, got {}",
+            volume,
+            indices.len()
+        ));
+    }
+
+    Ok(Structure {
+        size,
+        origin_pos,
+        blocks: Palette::from_names(&names, indices)?.blocks()?,
+    })
+}
+
+/// The old format stores the size and the blocks separately, so they have to be checked to match
+fn decode_bincode(data: &[u8]) -> Result<Structure, String> {
+    let structure = Structure::deserialize(data)
+        .map_err(|e| format!("error while trying serialize structure: {}", e))?;
+    let volume = palette::check_size(structure.size)?;
+    if structure.blocks.len() != volume {
+        return Err(format!(
+            "expected {} blocks for the size, got {}",
+            volume,
+            structure.blocks.len()
+        ));
+    }
+    Ok(structure)
+}
+
+/// Every index with how many times it repeats
+fn runs(indices: &[u32]) -> Vec<(u32, usize)> {
+    let mut runs: Vec<(u32, usize)> = Vec::new();
+    for index in indices {
+        match runs.last_mut() {
+            Some((last, length)) if last == index => *length += 1,
+            _ => runs.push((*index, 1)),
+        }
+    }
+    runs
+}
+
+fn write_varint(data: &mut Vec<u8>, mut value: u64) {
+    loop {
+        let byte = (value & 0x7F) as u8;
+        value >>= 7;
+        if value == 0 {
+            data.push(byte);
+            return;
+        }
+        data.push(byte | 0x80);
+    }
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, count: usize) -> Result<&'a [u8], String> {
+        if self.data.len() < count {
+            return Err("the file ends too early".to_string());
+        }
+        let (bytes, rest) = self.data.split_at(count);
+        self.data = rest;
+        Ok(bytes)
+    }
+
+    fn varint(&mut self) -> Result<u64, String> {
+        let mut value = 0;
+        for shift in (0..64).step_by(7) {
+            let byte = self.bytes(1)?[0];
+            value |= ((byte & 0x7F) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err("a number in the file is too large".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use valence::prelude::*;
+
+    use super::*;
+
+    fn structure() -> Structure {
+        let stairs = BlockState::OAK_STAIRS.set(PropName::Facing, PropValue::East);
+        let mut blocks = vec![BlockState::AIR; 3 * 4 * 5];
+        blocks[0] = BlockState::STONE;
+        blocks[7] = stairs;
+        blocks[8] = stairs;
+        blocks[59] = BlockState::STRUCTURE_VOID;
+        Structure {
+            size: IVec3::new(3, 4, 5),
+            origin_pos: IVec3::new(-1, 2, 0),
+            blocks,
+        }
+    }
+
+    #[test]
+    fn round_trips() {
+        let structure = structure();
+        let loaded = decode(&encode(&structure)).unwrap();
+        assert_eq!(loaded.size, structure.size);
+        assert_eq!(loaded.origin_pos, structure.origin_pos);
+        assert_eq!(loaded.blocks, structure.blocks);
+    }
+
+    #[test]
+    fn reads_the_old_format() {
+        let structure = structure();
+        let loaded = decode(&structure.serialize()).unwrap();
+        assert_eq!(loaded.blocks, structure.blocks);
+    }
+
+    #[test]
+    fn old_files_must_match_their_size() {
+        for size in [
+            IVec3::new(3, 2, 3),
+            IVec3::new(0, 2, 2),
+            IVec3::splat(i32::MAX),
+        ] {
+            let mut structure = structure();
+            structure.size = size;
+            assert!(decode(&structure.serialize()).is_err(), "{:?}", size);
+        }
+    }
+
+    #[test]
+    fn truncated_files_are_an_error() {
+        let data = encode(&structure());
+        for length in MAGIC.len()..data.len() {
+            assert!(decode(&data[..length]).is_err(), "length {}", length);
+        }
+    }
+
+    #[test]
+    fn huge_sizes_are_an_error() {
+        for size in [
+            IVec3::new(100_000, 100_000, 100_000),
+            IVec3::splat(i32::MAX),
+        ] {
+            let mut data = MAGIC.to_vec();
+            data.push(VERSION);
+            for value in size.to_array().into_iter().chain([0; 3]) {
+                data.extend_from_slice(&value.to_le_bytes());
+            }
+            // one block name and one run covering the whole size
+            write_varint(&mut data, 1);
+            write_varint(&mut data, 15);
+            data.extend_from_slice(b"minecraft:stone");
+            write_varint(&mut data, 1);
+            write_varint(&mut data, 0);
+            write_varint(&mut data, u64::MAX >> 1);
+            assert!(decode(&data).is_err(), "{:?}", size);
+        }
+    }
+
+    #[test]
+    fn varints_round_trip() {
+        for value in [0, 1, 127, 128, 300, u32::MAX as u64, u64::MAX] {
+            let mut data = Vec::new();
+            write_varint(&mut data, value);
+            assert_eq!(Reader { data: &data }.varint(), Ok(value));
+        }
+    }
+}
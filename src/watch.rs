@@ -0,0 +1,131 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use valence::prelude::*;
+
+use crate::{
+    bounds::{Bounds, Occupancy},
+    commands::{client_error, client_info},
+    origin::Origin,
+    save,
+    section::Section,
+    workspace::{load_structure, CurrentPath, Dirty, Editing, Plots, Workspace},
+    EditorLayer,
+};
+
+/// How many ticks there are between checking watched files
+const WATCH_INTERVAL: i64 = 20;
+
+pub struct WatchPlugin;
+
+impl Plugin for WatchPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, reload_watched);
+    }
+}
+
+/// Reloads the workspace when its file is changed by another program
+#[derive(Component, Default)]
+pub struct Watch {
+    path: Option<PathBuf>,
+    modified: Option<SystemTime>,
+}
+
+impl Watch {
+    /// Remembers the file as it is now, so it is only reloaded if it changes after this
+    pub fn record(&mut self, path: &Path) {
+        self.path = Some(path.to_path_buf());
+        self.modified = modified(path);
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|meta| meta.modified()).ok()
+}
+
+fn reload_watched(
+    mut workspaces: Query<(
+        Entity,
+        &Workspace,
+        &mut Watch,
+        &CurrentPath,
+        &Dirty,
+        &mut Bounds,
+        &mut Section,
+        &mut Occupancy,
+    )>,
+    mut origins: Query<&mut Origin>,
+    mut layer: Query<&mut ChunkLayer, With<EditorLayer>>,
+    mut editors: Query<(&mut Client, &Editing)>,
+    server: Res<Server>,
+    plots: Res<Plots>,
+) {
+    if server.current_tick() % WATCH_INTERVAL != 0 {
+        return;
+    }
+    let Ok(mut layer) = layer.get_single_mut() else {
+        return;
+    };
+    for (
+        entity,
+        workspace,
+        mut watch,
+        current_path,
+        dirty,
+        mut bounds,
+        mut section,
+        mut occupancy,
+    ) in &mut workspaces
+    {
+        let Some(path) = &current_path.0 else {
+            continue;
+        };
+        // the workspace was saved or loaded somewhere else
+        if watch.path.as_ref() != Some(path) {
+            watch.record(path);
+            continue;
+        }
+        if modified(path) == watch.modified {
+            continue;
+        }
+        watch.record(path);
+
+        let message = if dirty.0 {
+            Err(format!(
+                "'{}' changed on disk, but was not reloaded because it has unsaved changes",
+                path.display()
+            ))
+        } else {
+            match (
+                save::read_structure(path),
+                origins.get_mut(workspace.origin),
+            ) {
+                (Ok(structure), Ok(mut origin)) => load_structure(
+                    &mut origin,
+                    &mut bounds,
+                    &mut section,
+                    &mut occupancy,
+                    &mut layer,
+                    &structure,
+                    plots.center(workspace.plot),
+                )
+                .map(|_| format!("reloaded '{}'", path.display()))
+                .map_err(|e| format!("could not reload '{}', {}", path.display(), e)),
+                (Err(e), _) => Err(format!("could not reload '{}', {}", path.display(), e)),
+                (_, Err(_)) => continue,
+            }
+        };
+        for (mut client, editing) in &mut editors {
+            if editing.0 != entity {
+                continue;
+            }
+            match &message {
+                Ok(message) => client_info(&mut client, message.clone()),
+                Err(message) => client_error(&mut client, message.clone()),
+            }
+        }
+    }
+}
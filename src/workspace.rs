@@ -0,0 +1,267 @@
+use std::path::PathBuf;
+
+use valence::{math::IVec3, prelude::*};
+
+use crate::{
+    bounds::{union, Bounds, Occupancy},
+    config::{Role, SectionStyle},
+    origin::{self, Origin},
+    section::Section,
+    structure::Structure,
+};
+
+/// Width of the plot every workspace is placed in, along the x axis
+pub const PLOT_SIZE: i32 = 256;
+/// Depth of the plots along the z axis
+pub const PLOT_DEPTH: i32 = 480;
+
+/// An open structure, with its own bounds, origin and path
+#[derive(Component)]
+pub struct Workspace {
+    pub name: String,
+    /// username of the only builder allowed to edit the workspace
+    pub owner: String,
+    pub origin: Entity,
+    pub plot: i32,
+}
+
+impl Workspace {
+    pub fn can_edit(&self, username: &str, role: Role) -> bool {
+        match role {
+            Role::Viewer => false,
+            Role::Builder => self.owner == username,
+            Role::Admin => true,
+        }
+    }
+}
+
+/// marker component for the workspace a player gets when they first join,
+/// it is kept when they leave and can not be closed
+#[derive(Component)]
+pub struct Personal;
+
+#[derive(Component)]
+pub struct CurrentPath(pub Option<PathBuf>);
+
+/// Whether the structure has changed since it was last saved or loaded,
+/// should be changed with `set_if_neq` so change detection only triggers when it flips
+#[derive(Component, Default, PartialEq)]
+pub struct Dirty(pub bool);
+
+/// The workspace a client is editing
+#[derive(Component)]
+pub struct Editing(pub Entity);
+
+/// Shows the file name and whether there are unsaved changes above the workspace
+pub fn update_labels(
+    mut workspaces: Query<
+        (&Workspace, &CurrentPath, &Dirty, &mut Section),
+        Or<(Changed<CurrentPath>, Changed<Dirty>)>,
+    >,
+) {
+    for (workspace, path, dirty, mut section) in &mut workspaces {
+        let name = match path.0.as_ref().and_then(|path| path.file_name()) {
+            Some(file_name) => file_name.to_string_lossy().into_owned(),
+            None => format!("{} (not saved)", workspace.name),
+        };
+        let label = Some(if dirty.0 { name + "*" } else { name });
+        if section.label != label {
+            section.label = label;
+        }
+    }
+}
+
+/// Where the plots are, the first plot is centered on the spawn point and the others are next to it along x
+#[derive(Resource, Clone, Copy)]
+pub struct Plots {
+    pub spawn: BlockPos,
+}
+
+impl Plots {
+    /// The block the origin of a structure is placed at when it is loaded into the plot
+    pub fn center(&self, plot: i32) -> BlockPos {
+        self.spawn.offset(plot * PLOT_SIZE, 0, 0)
+    }
+
+    pub fn plot_of(&self, pos: BlockPos) -> i32 {
+        (pos.x - self.spawn.x + PLOT_SIZE / 2).div_euclid(PLOT_SIZE)
+    }
+
+    /// Player position when teleporting to the plot
+    pub fn player_spawn(&self, plot: i32) -> DVec3 {
+        let center = self.center(plot);
+        DVec3::new(
+            center.x as f64 + 0.5,
+            center.y as f64 + 1.0,
+            center.z as f64 + 0.5,
+        )
+    }
+}
+
+/// The plot closest to the center that is not in use
+pub fn free_plot(used: &[i32]) -> i32 {
+    (0..)
+        .flat_map(|i: i32| [i, -i - 1])
+        .find(|plot| !used.contains(plot))
+        .unwrap()
+}
+
+/// Adds a number to the name if it is taken
+pub fn unique_name(name: String, taken: &[&str]) -> String {
+    if !taken.contains(&name.as_str()) {
+        return name;
+    }
+    (2..)
+        .map(|i| format!("{}-{}", name, i))
+        .find(|candidate| !taken.contains(&candidate.as_str()))
+        .unwrap()
+}
+
+/// The structure `/new` creates
+pub fn empty_structure() -> Structure {
+    Structure {
+        size: IVec3::new(1, 1, 1),
+        origin_pos: IVec3::ZERO,
+        blocks: vec![BlockState::BEDROCK],
+    }
+}
+
+/// Loads the structure into a free plot and spawns a workspace for it
+#[allow(clippy::too_many_arguments)]
+pub fn spawn_workspace(
+    commands: &mut Commands,
+    layer_entity: Entity,
+    layer: &mut ChunkLayer,
+    name: String,
+    owner: String,
+    plots: &Plots,
+    plot: i32,
+    structure: &Structure,
+    path: Option<PathBuf>,
+    style: &SectionStyle,
+) -> Result<Entity, String> {
+    load_plot_chunks(layer, plots.center(plot));
+
+    let mut origin = Origin {
+        position: plots.center(plot),
+    };
+    let mut bounds = Bounds::default();
+    let mut section = Section {
+        position: origin.position,
+        layer: EntityLayerId(layer_entity),
+        ..Default::default()
+    };
+    style.apply(&mut section);
+    let mut occupancy = Occupancy::default();
+    load_structure(
+        &mut origin,
+        &mut bounds,
+        &mut section,
+        &mut occupancy,
+        layer,
+        structure,
+        origin.position,
+    )?;
+
+    let origin = origin::spawn_origin(commands, layer_entity, origin.position);
+    Ok(commands
+        .spawn((
+            Workspace {
+                name,
+                owner,
+                origin,
+                plot,
+            },
+            bounds,
+            section,
+            occupancy,
+            CurrentPath(path),
+            Dirty::default(),
+        ))
+        .id())
+}
+
+/// Replaces the blocks of the workspace with the structure, with its origin at the center of the plot.
+/// Nothing is changed if the structure does not fit in the plot.
+pub fn load_structure(
+    origin: &mut Origin,
+    bounds: &mut Bounds,
+    section: &mut Section,
+    occupancy: &mut Occupancy,
+    layer: &mut ChunkLayer,
+    structure: &Structure,
+    center: BlockPos,
+) -> Result<(), String> {
+    check_fits(
+        layer,
+        center,
+        (center - structure.origin_pos, structure.size),
+    )?;
+    clear(layer, section);
+    origin.position = center;
+    structure.render_to_layer(layer, origin.position);
+    section.size = structure.size;
+    section.position = origin.position - structure.origin_pos;
+    *occupancy = Occupancy::from_layer(layer, section.position, section.size);
+    // the saved size only differs from the blocks if the bounds were padded
+    bounds.locked = occupancy.extent() != Some((section.position, section.size));
+    Ok(())
+}
+
+/// Lowest corner and size of the plot around the center
+pub fn plot_area(layer: &ChunkLayer, center: BlockPos) -> (BlockPos, IVec3) {
+    (
+        BlockPos::new(
+            center.x - PLOT_SIZE / 2,
+            layer.min_y(),
+            center.z - PLOT_DEPTH / 2,
+        ),
+        IVec3::new(PLOT_SIZE, layer.height() as i32, PLOT_DEPTH),
+    )
+}
+
+/// Workspaces are kept within their plot, so they never overlap each other
+pub fn check_fits(
+    layer: &ChunkLayer,
+    center: BlockPos,
+    area: (BlockPos, IVec3),
+) -> Result<(), String> {
+    let plot = plot_area(layer, center);
+    if union(plot, area) == plot {
+        return Ok(());
+    }
+    Err(format!(
+        "{}x{}x{} blocks do not fit in the plot, which reaches {} blocks from the origin along x, \
+         {} along z, and goes from y {} to {}",
+        area.1.x,
+        area.1.y,
+        area.1.z,
+        PLOT_SIZE / 2,
+        PLOT_DEPTH / 2,
+        plot.0.y,
+        plot.0.y + plot.1.y - 1
+    ))
+}
+
+pub fn clear(layer: &mut ChunkLayer, section: &Section) {
+    for x in 0..section.size.x {
+        for y in 0..section.size.y {
+            for z in 0..section.size.z {
+                let pos = section.position + IVec3::new(x, y, z);
+                layer.set_block(pos, BlockState::AIR);
+            }
+        }
+    }
+}
+
+fn load_plot_chunks(layer: &mut ChunkLayer, center: BlockPos) {
+    let (start, size) = plot_area(layer, center);
+    let end = start + size - IVec3::ONE;
+    for z in start.z.div_euclid(16)..=end.z.div_euclid(16) {
+        for x in start.x.div_euclid(16)..=end.x.div_euclid(16) {
+            if layer.chunk([x, z]).is_none() {
+                layer.insert_chunk([x, z], UnloadedChunk::new());
+            }
+        }
+    }
+}
@@ -0,0 +1,406 @@
+use std::{fs, path::PathBuf};
+
+use valence::{
+    command::{handler::CommandResultEvent, parsers::GreedyString, Command},
+    interact_block::InteractBlockEvent,
+    math::IVec3,
+    prelude::*,
+    testing::ScenarioSingleClient,
+};
+use valence_editor::{
+    commands::{
+        BoundsCommand, LoadCommand, MergeCommand, OriginCommand, RestoreCommand, SaveCommand,
+        SnapshotCommand, VoidCommand,
+    },
+    config::Config,
+    origin::Origin,
+    save,
+    section::Section,
+    snapshot,
+    structure::Structure,
+    void::STRUCTURE_VOID,
+    workspace::{Dirty, Editing, Workspace, PLOT_SIZE},
+    EditorLayer, EditorPlugin, JoinEditor,
+};
+
+/// An app with the editor and a single client editing their own workspace
+struct Editor {
+    app: App,
+    client: Entity,
+}
+
+impl Editor {
+    fn new() -> Self {
+        let ScenarioSingleClient {
+            mut app,
+            client,
+            layer,
+            ..
+        } = ScenarioSingleClient::new();
+        app.world.entity_mut(layer).insert(EditorLayer);
+        app.insert_resource(Config::default());
+        app.add_plugins(EditorPlugin {
+            spawn_layer: false,
+            join_on_connect: false,
+            ..Default::default()
+        });
+        app.world.entity_mut(client).insert(JoinEditor);
+        // the workspace is spawned with commands, so it exists after the second update
+        app.update();
+        app.update();
+        Self { app, client }
+    }
+
+    fn workspace(&self) -> Entity {
+        self.app.world.get::<Editing>(self.client).unwrap().0
+    }
+
+    fn command<T: Command + Send + Sync>(&mut self, result: T) {
+        self.app.world.send_event(CommandResultEvent {
+            result,
+            executor: self.client,
+            modifiers: Default::default(),
+        });
+        self.app.update();
+    }
+
+    /// Places the item held in the first hotbar slot at the position
+    fn place(&mut self, pos: BlockPos, item: ItemKind) {
+        self.app
+            .world
+            .get_mut::<Inventory>(self.client)
+            .unwrap()
+            .set_slot(36, ItemStack::new(item, 1, None));
+        self.app.world.send_event(InteractBlockEvent {
+            client: self.client,
+            hand: Hand::Main,
+            position: pos.offset(0, -1, 0),
+            face: Direction::Up,
+            cursor_pos: Vec3::new(0.5, 1.0, 0.5),
+            head_inside_block: false,
+            sequence: 0,
+        });
+        self.app.update();
+    }
+
+    fn dig(&mut self, pos: BlockPos) {
+        self.app.world.send_event(DiggingEvent {
+            client: self.client,
+            position: pos,
+            direction: Direction::Up,
+            state: DiggingState::Start,
+        });
+        self.app.update();
+    }
+
+    fn block(&mut self, pos: BlockPos) -> BlockState {
+        let mut layers = self
+            .app
+            .world
+            .query_filtered::<&ChunkLayer, With<EditorLayer>>();
+        layers
+            .single(&self.app.world)
+            .block(pos)
+            .map(|block| block.state)
+            .unwrap_or(BlockState::AIR)
+    }
+
+    fn section(&self) -> (BlockPos, IVec3) {
+        let section = self.app.world.get::<Section>(self.workspace()).unwrap();
+        (section.position, section.size)
+    }
+
+    fn origin(&self) -> BlockPos {
+        let workspace = self.app.world.get::<Workspace>(self.workspace()).unwrap();
+        self.app
+            .world
+            .get::<Origin>(workspace.origin)
+            .unwrap()
+            .position
+    }
+
+    fn dirty(&self) -> bool {
+        self.app.world.get::<Dirty>(self.workspace()).unwrap().0
+    }
+}
+
+/// A path in the temp folder that is removed when dropped
+struct TempFile(PathBuf);
+
+impl TempFile {
+    fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!(
+            "valence_editor_{}_{}.vstruc",
+            std::process::id(),
+            name
+        ));
+        let _ = fs::remove_file(&path);
+        Self(path)
+    }
+
+    fn arg(&self) -> GreedyString {
+        GreedyString(self.0.to_string_lossy().into_owned())
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+        for backup in 1..=save::BACKUPS {
+            let _ = fs::remove_file(save::backup_path(&self.0, backup));
+        }
+        let _ = fs::remove_dir_all(snapshot::folder(&self.0));
+    }
+}
+
+#[test]
+fn new_workspace_contains_one_block() {
+    let mut editor = Editor::new();
+    let origin = editor.origin();
+    assert_eq!(editor.section(), (origin, IVec3::ONE));
+    assert_eq!(editor.block(origin), BlockState::BEDROCK);
+    assert!(!editor.dirty());
+}
+
+#[test]
+fn origin_moves_in_each_direction() {
+    let mut editor = Editor::new();
+    for (command, offset) in [
+        (OriginCommand::Up, IVec3::new(0, 1, 0)),
+        (OriginCommand::Down, IVec3::new(0, -1, 0)),
+        (OriginCommand::North, IVec3::new(0, 0, -1)),
+        (OriginCommand::South, IVec3::new(0, 0, 1)),
+        (OriginCommand::East, IVec3::new(1, 0, 0)),
+        (OriginCommand::West, IVec3::new(-1, 0, 0)),
+    ] {
+        let before = editor.origin();
+        editor.command(command.clone());
+        assert_eq!(
+            editor.origin(),
+            before + offset,
+            "{:?} moved the origin the wrong way",
+            command
+        );
+    }
+    assert!(editor.dirty());
+}
+
+#[test]
+fn placing_blocks_grows_the_section() {
+    let mut editor = Editor::new();
+    let origin = editor.origin();
+    let pos = origin.offset(2, 1, -3);
+    editor.place(pos, ItemKind::Stone);
+
+    assert_eq!(editor.block(pos), BlockState::STONE);
+    assert_eq!(
+        editor.section(),
+        (origin.offset(0, 0, -3), IVec3::new(3, 2, 4))
+    );
+    assert!(editor.dirty());
+}
+
+#[test]
+fn digging_blocks_shrinks_the_section() {
+    let mut editor = Editor::new();
+    let origin = editor.origin();
+    let pos = origin.offset(4, 0, 0);
+    editor.place(pos, ItemKind::Stone);
+    editor.dig(pos);
+
+    assert_eq!(editor.block(pos), BlockState::AIR);
+    assert_eq!(editor.section(), (origin, IVec3::ONE));
+}
+
+#[test]
+fn locked_bounds_do_not_shrink() {
+    let mut editor = Editor::new();
+    let origin = editor.origin();
+    let pos = origin.offset(4, 0, 0);
+    editor.place(pos, ItemKind::Stone);
+    editor.command(BoundsCommand::Lock);
+    editor.dig(pos);
+
+    assert_eq!(editor.section(), (origin, IVec3::new(5, 1, 1)));
+}
+
+#[test]
+fn bounds_can_not_shrink_past_the_blocks() {
+    let mut editor = Editor::new();
+    let origin = editor.origin();
+    editor.command(BoundsCommand::Expand {
+        direction: "up".into(),
+        amount: 2,
+    });
+    assert_eq!(editor.section(), (origin, IVec3::new(1, 3, 1)));
+
+    editor.command(BoundsCommand::Contract {
+        direction: "up".into(),
+        amount: 3,
+    });
+    assert_eq!(editor.section(), (origin, IVec3::new(1, 3, 1)));
+}
+
+#[test]
+fn bounds_stay_within_the_plot() {
+    let mut editor = Editor::new();
+    let origin = editor.origin();
+    for (direction, amount) in [
+        ("east", PLOT_SIZE),
+        ("up", i32::MAX),
+        ("down", i32::MAX),
+        ("up", 0),
+    ] {
+        editor.command(BoundsCommand::Expand {
+            direction: direction.into(),
+            amount,
+        });
+    }
+    editor.command(BoundsCommand::Contract {
+        direction: "up".into(),
+        amount: i32::MIN,
+    });
+
+    assert_eq!(editor.section(), (origin, IVec3::ONE));
+}
+
+#[test]
+fn void_fill_replaces_air_within_the_bounds() {
+    let mut editor = Editor::new();
+    let origin = editor.origin();
+    editor.command(BoundsCommand::Expand {
+        direction: "east".into(),
+        amount: 1,
+    });
+    editor.command(VoidCommand::Fill);
+
+    assert_eq!(editor.block(origin), BlockState::BEDROCK);
+    assert_eq!(editor.block(origin.offset(1, 0, 0)), STRUCTURE_VOID);
+    assert_eq!(editor.block(origin.offset(2, 0, 0)), BlockState::AIR);
+}
+
+#[test]
+fn saved_structures_load_back() {
+    let file = TempFile::new("saved_structures_load_back");
+    let mut editor = Editor::new();
+    let origin = editor.origin();
+    editor.place(origin.offset(1, 0, 0), ItemKind::Stone);
+    editor.command(OriginCommand::Up);
+    editor.command(SaveCommand {
+        path: Some(file.arg()),
+    });
+
+    assert!(!editor.dirty());
+    let structure = save::read_structure(&file.0).unwrap();
+    assert_eq!(structure.size, IVec3::new(2, 1, 1));
+    assert_eq!(structure.origin_pos, IVec3::new(0, 1, 0));
+
+    editor.place(origin.offset(0, 0, 3), ItemKind::Dirt);
+    editor.command(LoadCommand {
+        path: GreedyString(format!("{} force", file.arg().0)),
+    });
+
+    let origin = editor.origin();
+    assert!(!editor.dirty());
+    assert_eq!(
+        editor.section(),
+        (origin.offset(0, -1, 0), IVec3::new(2, 1, 1))
+    );
+    assert_eq!(editor.block(origin.offset(1, -1, 0)), BlockState::STONE);
+}
+
+#[test]
+fn snapshots_restore_the_blocks() {
+    let file = TempFile::new("snapshots_restore_the_blocks");
+    let mut editor = Editor::new();
+    let origin = editor.origin();
+    let pos = origin.offset(1, 0, 0);
+    editor.command(SaveCommand {
+        path: Some(file.arg()),
+    });
+    editor.place(pos, ItemKind::Stone);
+    editor.command(SnapshotCommand {
+        name: GreedyString("stone".into()),
+    });
+    editor.dig(pos);
+
+    // unsaved changes are kept without force
+    editor.command(RestoreCommand {
+        name: GreedyString("stone".into()),
+    });
+    assert_eq!(editor.block(pos), BlockState::AIR);
+
+    editor.command(RestoreCommand {
+        name: GreedyString("stone force".into()),
+    });
+    let origin = editor.origin();
+    assert_eq!(editor.block(origin.offset(1, 0, 0)), BlockState::STONE);
+    assert_eq!(editor.section(), (origin, IVec3::new(2, 1, 1)));
+    assert!(editor.dirty());
+}
+
+#[test]
+fn editor_waits_for_the_layer() {
+    let ScenarioSingleClient {
+        mut app,
+        client,
+        layer,
+        ..
+    } = ScenarioSingleClient::new();
+    app.insert_resource(Config::default());
+    app.add_plugins(EditorPlugin {
+        spawn_layer: false,
+        join_on_connect: false,
+        ..Default::default()
+    });
+    app.world.entity_mut(client).insert(JoinEditor);
+    app.update();
+    app.update();
+    assert!(app.world.get::<Editing>(client).is_none());
+
+    app.world.entity_mut(layer).insert(EditorLayer);
+    app.update();
+    app.update();
+    assert!(app.world.get::<Editing>(client).is_some());
+}
+
+#[test]
+fn structures_wider_than_the_plot_are_not_loaded() {
+    let file = TempFile::new("structures_wider_than_the_plot_are_not_loaded");
+    let size = IVec3::new(PLOT_SIZE + 1, 1, 1);
+    let structure = Structure {
+        size,
+        origin_pos: IVec3::ZERO,
+        blocks: vec![BlockState::STONE; size.x as usize],
+    };
+    save::save_structure(&file.0, &structure).unwrap();
+    let mut editor = Editor::new();
+    let origin = editor.origin();
+    editor.command(LoadCommand { path: file.arg() });
+
+    assert_eq!(editor.section(), (origin, IVec3::ONE));
+    assert_eq!(editor.block(origin), BlockState::BEDROCK);
+}
+
+#[test]
+fn merges_wider_than_the_plot_are_rejected() {
+    let base = TempFile::new("merges_wider_than_the_plot_are_rejected_base");
+    let theirs = TempFile::new("merges_wider_than_the_plot_are_rejected_theirs");
+    let size = IVec3::new(PLOT_SIZE + 1, 1, 1);
+    let structure = Structure {
+        size,
+        origin_pos: IVec3::ZERO,
+        blocks: vec![BlockState::STONE; size.x as usize],
+    };
+    save::save_structure(&theirs.0, &structure).unwrap();
+    let mut editor = Editor::new();
+    let origin = editor.origin();
+    editor.command(SaveCommand {
+        path: Some(base.arg()),
+    });
+    editor.command(MergeCommand { path: theirs.arg() });
+
+    assert_eq!(editor.section(), (origin, IVec3::ONE));
+    assert_eq!(editor.block(origin), BlockState::BEDROCK);
+    assert!(!editor.dirty());
+}